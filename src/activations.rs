@@ -1,18 +1,19 @@
-#![allow(dead_code)]
-
-use core::f64;
-
-use ndarray::{Array2, Axis};
-
-use crate::utils::diagflat;
-
-pub struct ReLU {
-    pub outputs: Option<Array2<f64>>,
-    pub inputs: Option<Array2<f64>>,
-    pub dinputs: Option<Array2<f64>>
+use ndarray::{Array2, ArrayD, Axis};
+
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    module::Module, 
+    utils::{check_shape, diagflat, reshape, to_rows}
+};
+
+pub struct ReLU<F: Float = f64> {
+    pub outputs: Option<ArrayD<F>>,
+    pub inputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>
 }
 
-impl ReLU {
+impl<F: Float> ReLU<F> {
     pub fn new() -> Self {
         ReLU { 
             outputs: None, 
@@ -20,37 +21,48 @@ impl ReLU {
             dinputs: None
         }
     }
+}
 
-    pub fn forward(&mut self, inputs: &Array2<f64>) {
+impl<F: Float> Default for ReLU<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Float> Module<F> for ReLU<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
         self.inputs = Some(inputs.clone());
-        self.outputs = Some(inputs.mapv(|v| v.max(0.0)));
+        self.outputs = Some(inputs.mapv(|v| v.max(F::zero())));
+        Ok(())
     }
 
-    pub fn backward(&mut self, dvalues: &Array2<f64>) {
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let outputs = self.outputs()?;
+        check_shape(dvalues, outputs.shape())?;
+
         let mut dinputs = dvalues.clone();
-        dinputs.zip_mut_with(
-            self.outputs.as_ref().expect("No output set. Make sure to call `forward` before `backward`."), 
-            |d, &zv| if zv <= 0.0 { *d = 0.0 }
-        );
+        dinputs.zip_mut_with(outputs, |d, &zv| if zv <= F::zero() { *d = F::zero() });
         self.dinputs = Some(dinputs);
+        Ok(())
     }
 
-    pub fn outputs(&self) -> &Array2<f64> {
-        self.outputs.as_ref().expect("No output set. Make sure to call `forward` first.")
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
     }
 
-    pub fn dinputs(&self) -> &Array2<f64> {
-        self.dinputs.as_ref().expect("No dinputs set. Make sure to call `backward` first.")
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
     }
 }
 
-pub struct Softmax {
-    pub inputs: Option<Array2<f64>>,
-    pub outputs: Option<Array2<f64>>,
-    pub dinputs: Option<Array2<f64>>
+/// Softmax over the last axis.
+pub struct Softmax<F: Float = f64> {
+    pub inputs: Option<ArrayD<F>>,
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>
 }
 
-impl Softmax {
+impl<F: Float> Softmax<F> {
     pub fn new() -> Self {
         Softmax{
             inputs: None,
@@ -58,30 +70,42 @@ impl Softmax {
             dinputs: None
         }
     }
+}
+
+impl<F: Float> Default for Softmax<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    pub fn forward(&mut self, inputs: &Array2<f64>) {
+impl<F: Float> Module<F> for Softmax<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
         self.inputs = Some(inputs.clone());
-        let sample_maxes = inputs.map_axis(
-            Axis(1), |r| r.fold(f64::NEG_INFINITY, |a, &b| a.max(b))
+        let rows = to_rows(inputs)?;
+        let sample_maxes = rows.map_axis(
+            Axis(1), |r| r.fold(F::neg_infinity(), |a, &b| a.max(b))
         ).insert_axis(Axis(1));
-        let input_norm = inputs - sample_maxes;
-        let exp_values = input_norm.mapv(f64::exp);
+        let input_norm = &rows - &sample_maxes;
+        let exp_values = input_norm.mapv(F::exp);
         let sample_sum = exp_values.sum_axis(Axis(1)).insert_axis(Axis(1));
-        let probs = &exp_values / &sample_sum;
+        let probs = reshape(&exp_values / &sample_sum, inputs.shape());
         self.outputs = Some(probs);
+        Ok(())
     }
 
-    pub fn backward(&mut self, dvalues: Array2<f64>) {
-        let mut dinputs: Array2<f64> = Array2::zeros(dvalues.raw_dim());
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let outputs = self.outputs()?;
+        check_shape(dvalues, outputs.shape())?;
+        let (outputs, dvalues_rows) = (to_rows(outputs)?, to_rows(dvalues)?);
+
+        let mut dinputs: Array2<F> = Array2::zeros(dvalues_rows.raw_dim());
 
         for 
             (index, (single_output, single_dvalues)) 
-            in self.outputs
-                .as_ref()
-                .expect("No output set. Make sure to call `forward` first.")
+            in outputs
                 .rows()
                 .into_iter()
-                .zip(dvalues.rows())
+                .zip(dvalues_rows.rows())
                 .enumerate() {
                     let output_col = &single_output
                         .into_shape((single_output.len(), 1))
@@ -93,10 +117,15 @@ impl Softmax {
                     dinputs.row_mut(index).assign(&jacobian_matrix.dot(&single_dvalues));
         }
 
-        self.dinputs = Some(dinputs);
+        self.dinputs = Some(reshape(dinputs, dvalues.shape()));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
     }
 
-    pub fn outputs(&self) -> &Array2<f64> {
-        self.outputs.as_ref().expect("No output set. Make sure to call `forward` first.")
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
     }
 }
\ No newline at end of file
@@ -0,0 +1,267 @@
+use ndarray::{s, Array2, ArrayD, Axis, Ix3};
+
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    initializers::Initializer, 
+    layer::Layer, 
+    module::{Module, Param}, 
+    utils::{check_shape, reshape, to_rank, to_rows}
+};
+
+fn projection<F: Float>(embed_dim: usize) -> Layer<F> {
+    Layer::with_initializers(embed_dim, embed_dim, Initializer::XavierUniform, Initializer::Zeros)
+}
+
+/// Multi-head scaled dot-product self-attention over (batch, time, embed_dim) sequences. Queries, keys
+/// and values are dense projections of the same input, and the heads' contexts are joined by a dense
+/// output projection. Padded steps are excluded through `set_padding_mask`.
+pub struct MultiHeadAttention<F: Float = f64> {
+    pub num_heads: usize,
+    /// Stops every step from attending to later steps.
+    pub causal: bool,
+
+    pub query: Layer<F>,
+    pub key: Layer<F>,
+    pub value: Layer<F>,
+    pub output: Layer<F>,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    // Padded positions of the next batch, of shape (batch, time), that no step may attend to.
+    padding_mask: Option<Array2<bool>>,
+    // Attention weights of shape (time, time), indexed by `sample * num_heads + head`.
+    attention: Vec<Array2<F>>
+}
+
+impl<F: Float> MultiHeadAttention<F> {
+    /// `embed_dim` must split evenly across `num_heads`.
+    pub fn new(embed_dim: usize, num_heads: usize, causal: bool) -> Result<Self> {
+        if num_heads == 0 || !embed_dim.is_multiple_of(num_heads) {
+            return Err(NnError::InvalidHyperparameter(format!(
+                "embed_dim {} is not divisible into {} heads", embed_dim, num_heads
+            )));
+        }
+
+        Ok(MultiHeadAttention {
+            num_heads,
+            causal,
+            query: projection(embed_dim),
+            key: projection(embed_dim),
+            value: projection(embed_dim),
+            output: projection(embed_dim),
+            outputs: None,
+            dinputs: None,
+            padding_mask: None,
+            attention: Vec::new()
+        })
+    }
+
+    pub fn embed_dim(&self) -> usize {
+        self.query.weights.nrows()
+    }
+
+    /// Attention weights of `head` for `sample` from the last forward pass, rows attending over columns.
+    pub fn attention_weights(&self, sample: usize, head: usize) -> Option<&Array2<F>> {
+        self.attention.get(sample * self.num_heads + head)
+    }
+
+    fn head_dim(&self) -> usize {
+        self.embed_dim() / self.num_heads
+    }
+
+    fn masked(&self, sample: usize, query: usize, key: usize) -> bool {
+        (self.causal && key > query) || self.padding_mask.as_ref().is_some_and(|mask| mask[[sample, key]])
+    }
+}
+
+impl<F: Float> Module<F> for MultiHeadAttention<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let embed_dim = self.embed_dim();
+        let (n_samples, steps, _) = to_rank::<F, Ix3>(inputs)?.dim();
+        check_shape(inputs, &[n_samples, steps, embed_dim])?;
+
+        if let Some(mask) = &self.padding_mask {
+            if mask.dim() != (n_samples, steps) {
+                return Err(NnError::ShapeMismatch { expected: vec![n_samples, steps], found: mask.shape().to_vec() });
+            }
+        }
+
+        self.query.forward(inputs)?;
+        self.key.forward(inputs)?;
+        self.value.forward(inputs)?;
+        let q = to_rows(self.query.outputs()?)?;
+        let k = to_rows(self.key.outputs()?)?;
+        let v = to_rows(self.value.outputs()?)?;
+
+        let head_dim = self.head_dim();
+        let scale = F::cast(1.0 / (head_dim as f64).sqrt());
+        let mut context = Array2::zeros(q.raw_dim());
+        let mut attention = Vec::with_capacity(n_samples * self.num_heads);
+
+        for sample in 0..n_samples {
+            let rows = sample * steps..(sample + 1) * steps;
+            for head in 0..self.num_heads {
+                let cols = head * head_dim..(head + 1) * head_dim;
+                let q_head = q.slice(s![rows.clone(), cols.clone()]);
+                let k_head = k.slice(s![rows.clone(), cols.clone()]);
+                let v_head = v.slice(s![rows.clone(), cols.clone()]);
+
+                let mut weights = q_head.dot(&k_head.t()) * scale;
+                for ((i, j), score) in weights.indexed_iter_mut() {
+                    if self.masked(sample, i, j) {
+                        *score = F::neg_infinity();
+                    }
+                }
+
+                // Row-wise softmax; a row with every key masked attends to nothing.
+                for mut row in weights.rows_mut() {
+                    let max = row.fold(F::neg_infinity(), |max, &score| max.max(score));
+                    if max == F::neg_infinity() {
+                        row.fill(F::zero());
+                        continue;
+                    }
+                    row.mapv_inplace(|score| (score - max).exp());
+                    let sum = row.sum();
+                    row.mapv_inplace(|score| score / sum);
+                }
+
+                context.slice_mut(s![rows.clone(), cols]).assign(&weights.dot(&v_head));
+                attention.push(weights);
+            }
+        }
+
+        self.output.forward(&reshape(context, &[n_samples, steps, embed_dim]))?;
+        self.outputs = Some(self.output.outputs()?.clone());
+        self.attention = attention;
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+
+        let (embed_dim, head_dim) = (self.embed_dim(), self.head_dim());
+        let (n_samples, steps) = (dvalues.len_of(Axis(0)), dvalues.len_of(Axis(1)));
+        self.output.backward(dvalues)?;
+        let dcontext = to_rows(self.output.dinputs()?)?;
+        let q = to_rows(self.query.outputs()?)?;
+        let k = to_rows(self.key.outputs()?)?;
+        let v = to_rows(self.value.outputs()?)?;
+
+        let scale = F::cast(1.0 / (head_dim as f64).sqrt());
+        let mut dq = Array2::zeros(q.raw_dim());
+        let mut dk = Array2::zeros(k.raw_dim());
+        let mut dv = Array2::zeros(v.raw_dim());
+
+        for sample in 0..n_samples {
+            let rows = sample * steps..(sample + 1) * steps;
+            for head in 0..self.num_heads {
+                let cols = head * head_dim..(head + 1) * head_dim;
+                let weights = &self.attention[sample * self.num_heads + head];
+                let dcontext_head = dcontext.slice(s![rows.clone(), cols.clone()]);
+
+                dv.slice_mut(s![rows.clone(), cols.clone()]).assign(&weights.t().dot(&dcontext_head));
+                let dweights = dcontext_head.dot(&v.slice(s![rows.clone(), cols.clone()]).t());
+                let row_dots = (&dweights * weights).sum_axis(Axis(1)).insert_axis(Axis(1));
+                let dscores = (dweights - &row_dots) * weights * scale;
+
+                dq.slice_mut(s![rows.clone(), cols.clone()]).assign(&dscores.dot(&k.slice(s![rows.clone(), cols.clone()])));
+                dk.slice_mut(s![rows.clone(), cols.clone()]).assign(&dscores.t().dot(&q.slice(s![rows.clone(), cols])));
+            }
+        }
+
+        let shape = [n_samples, steps, embed_dim];
+        self.query.backward(&reshape(dq, &shape))?;
+        self.key.backward(&reshape(dk, &shape))?;
+        self.value.backward(&reshape(dv, &shape))?;
+        self.dinputs = Some(self.query.dinputs()? + self.key.dinputs()? + self.value.dinputs()?);
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let mut params = self.query.params()?;
+        params.extend(self.key.params()?);
+        params.extend(self.value.params()?);
+        params.extend(self.output.params()?);
+        Ok(params)
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.query.set_trainable(trainable);
+        self.key.set_trainable(trainable);
+        self.value.set_trainable(trainable);
+        self.output.set_trainable(trainable);
+    }
+
+    /// Padded keys are never attended to; the mask must match the (batch, time) axes of the next batch.
+    fn set_padding_mask(&mut self, mask: Option<&Array2<bool>>) {
+        self.padding_mask = mask.cloned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, s};
+
+    use super::*;
+    use crate::testing::{assert_gradients, random};
+
+    #[test]
+    fn attention_gradients() {
+        let mask = array![[false, false, false, false], [false, false, true, true]];
+        for causal in [false, true] {
+            let mut attention = MultiHeadAttention::new(4, 2, causal).unwrap();
+            assert_gradients(&mut attention, &random(&[2, 4, 4], 1));
+            attention.set_padding_mask(Some(&mask));
+            assert_gradients(&mut attention, &random(&[2, 4, 4], 1));
+        }
+    }
+
+    #[test]
+    fn padding_is_never_attended_to() {
+        let mut attention = MultiHeadAttention::new(4, 2, false).unwrap();
+        let inputs = random(&[1, 3, 4], 1);
+        attention.forward(&inputs).unwrap();
+        let unpadded = attention.outputs().unwrap().clone();
+
+        // Two padded steps of arbitrary values must not change the outputs of the three real ones.
+        let mut padded = random(&[1, 5, 4], 2);
+        padded.slice_mut(s![.., ..3, ..]).assign(&inputs);
+        attention.set_padding_mask(Some(&array![[false, false, false, true, true]]));
+        attention.forward(&padded).unwrap();
+
+        let outputs = attention.outputs().unwrap().slice(s![.., ..3, ..]).into_dyn();
+        assert!((&outputs - &unpadded).iter().all(|diff| diff.abs() < 1e-12));
+        for head in 0..2 {
+            let weights = attention.attention_weights(0, head).unwrap();
+            assert!(weights.slice(s![.., 3..]).iter().all(|&weight| weight == 0.0));
+        }
+    }
+
+    #[test]
+    fn causal_steps_ignore_the_future() {
+        let mut attention = MultiHeadAttention::new(4, 1, true).unwrap();
+        attention.forward(&random(&[1, 4, 4], 1)).unwrap();
+        let weights = attention.attention_weights(0, 0).unwrap();
+        for ((query, key), &weight) in weights.indexed_iter() {
+            assert_eq!(weight == 0.0, key > query, "query {} key {}", query, key);
+        }
+    }
+
+    #[test]
+    fn rejects_masks_of_another_batch() {
+        let mut attention: MultiHeadAttention = MultiHeadAttention::new(4, 2, false).unwrap();
+        attention.set_padding_mask(Some(&Array2::from_elem((3, 4), false)));
+        assert!(matches!(attention.forward(&random(&[2, 4, 4], 1)), Err(NnError::ShapeMismatch { .. })));
+        assert!(matches!(MultiHeadAttention::<f64>::new(6, 4, false), Err(NnError::InvalidHyperparameter(_))));
+    }
+}
\ No newline at end of file
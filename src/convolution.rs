@@ -0,0 +1,497 @@
+use ndarray::{Array1, Array2, Array4, ArrayD, ArrayView2, ArrayView3, ArrayViewMut3, Axis, CowArray, Ix2, Ix3, Ix4};
+
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    initializers::Initializer, 
+    module::{Module, Param, ParamId}, 
+    utils::{check_shape, reshape, to_rank}
+};
+
+// Sizes shared by the im2col/col2im passes. Padding may be asymmetric so causal 1D convolutions can reuse it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Geometry {
+    pub(crate) channels: usize,
+    pub(crate) height: usize,
+    pub(crate) width: usize,
+    pub(crate) kernel: (usize, usize),
+    pub(crate) stride: (usize, usize),
+    pub(crate) dilation: (usize, usize),
+    pub(crate) pad_top: usize,
+    pub(crate) pad_left: usize,
+    pub(crate) out_height: usize,
+    pub(crate) out_width: usize
+}
+
+impl Geometry {
+    pub(crate) fn new(
+        (channels, height, width): (usize, usize, usize), 
+        kernel: (usize, usize), 
+        stride: (usize, usize), 
+        dilation: (usize, usize), 
+        (pad_top, pad_bottom): (usize, usize), 
+        (pad_left, pad_right): (usize, usize)
+    ) -> Result<Self> {
+        if [kernel.0, kernel.1, stride.0, stride.1, dilation.0, dilation.1].contains(&0) {
+            return Err(NnError::InvalidHyperparameter("kernel size, stride and dilation must be greater than zero".to_string()));
+        }
+
+        let span = (dilation.0 * (kernel.0 - 1) + 1, dilation.1 * (kernel.1 - 1) + 1);
+        let padded = (height + pad_top + pad_bottom, width + pad_left + pad_right);
+        if span.0 > padded.0 || span.1 > padded.1 {
+            return Err(NnError::InvalidHyperparameter(format!(
+                "dilated kernel {:?} is larger than the padded input {:?}", span, padded
+            )));
+        }
+
+        Ok(Geometry {
+            channels,
+            height,
+            width,
+            kernel,
+            stride,
+            dilation,
+            pad_top,
+            pad_left,
+            out_height: (padded.0 - span.0) / stride.0 + 1,
+            out_width: (padded.1 - span.1) / stride.1 + 1
+        })
+    }
+
+    pub(crate) fn input_size(&self) -> usize {
+        self.channels * self.height * self.width
+    }
+
+    fn patch_size(&self) -> usize {
+        self.channels * self.kernel.0 * self.kernel.1
+    }
+
+    pub(crate) fn output_positions(&self) -> usize {
+        self.out_height * self.out_width
+    }
+
+    // Flattens (batch, channels, height, width) `inputs` into one row per sample, checking their shape.
+    pub(crate) fn image_rows<'a, F: Float>(&self, inputs: &'a ArrayD<F>) -> Result<CowArray<'a, F, Ix2>> {
+        let n_samples = to_rank::<F, Ix4>(inputs)?.len_of(Axis(0));
+        check_shape(inputs, &[n_samples, self.channels, self.height, self.width])?;
+        Ok(inputs.to_shape((n_samples, self.input_size())).expect("Element count is unchanged"))
+    }
+
+    // Input coordinate read by output position `out` through kernel tap `tap`, if it is not padding.
+    pub(crate) fn source(&self, out: usize, tap: usize, stride: usize, dilation: usize, pad: usize, size: usize) -> Option<usize> {
+        (out * stride + tap * dilation).checked_sub(pad).filter(|&i| i < size)
+    }
+
+    // Unrolls every receptive field of the flattened NCHW `inputs` into one row per (sample, output position).
+    fn im2col<F: Float>(&self, inputs: ArrayView2<'_, F>) -> Array2<F> {
+        let (kh, kw) = self.kernel;
+        let mut columns = Array2::zeros((inputs.nrows() * self.output_positions(), self.patch_size()));
+
+        for (sample, x) in inputs.outer_iter().enumerate() {
+            let row_offset = sample * self.output_positions();
+            for c in 0..self.channels {
+                for i in 0..kh {
+                    for j in 0..kw {
+                        let column = (c * kh + i) * kw + j;
+                        for oh in 0..self.out_height {
+                            let Some(h) = self.source(oh, i, self.stride.0, self.dilation.0, self.pad_top, self.height) else { continue };
+                            for ow in 0..self.out_width {
+                                let Some(w) = self.source(ow, j, self.stride.1, self.dilation.1, self.pad_left, self.width) else { continue };
+                                columns[[row_offset + oh * self.out_width + ow, column]] = x[(c * self.height + h) * self.width + w];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        columns
+    }
+
+    // Inverse of `im2col`: sums every column entry back into the input position it was read from.
+    fn col2im<F: Float>(&self, columns: &Array2<F>, n_samples: usize) -> Array2<F> {
+        let (kh, kw) = self.kernel;
+        let mut inputs = Array2::zeros((n_samples, self.input_size()));
+
+        for (sample, mut x) in inputs.outer_iter_mut().enumerate() {
+            let row_offset = sample * self.output_positions();
+            for c in 0..self.channels {
+                for i in 0..kh {
+                    for j in 0..kw {
+                        let column = (c * kh + i) * kw + j;
+                        for oh in 0..self.out_height {
+                            let Some(h) = self.source(oh, i, self.stride.0, self.dilation.0, self.pad_top, self.height) else { continue };
+                            for ow in 0..self.out_width {
+                                let Some(w) = self.source(ow, j, self.stride.1, self.dilation.1, self.pad_left, self.width) else { continue };
+                                x[(c * self.height + h) * self.width + w] += columns[[row_offset + oh * self.out_width + ow, column]];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        inputs
+    }
+}
+
+// (N * positions, channels) rows, as produced by a matrix product over im2col rows, to flattened (N, channels * positions).
+fn positions_to_channels<F: Float>(values: Array2<F>, n_samples: usize, positions: usize) -> Array2<F> {
+    let channels = values.ncols();
+    values
+        .into_shape((n_samples, positions, channels))
+        .expect("Rows hold `positions` entries per sample")
+        .permuted_axes([0, 2, 1])
+        .as_standard_layout()
+        .into_owned()
+        .into_shape((n_samples, channels * positions))
+        .expect("Standard layout reshapes without copying")
+}
+
+// Inverse of `positions_to_channels`.
+fn channels_to_positions<F: Float>(values: ArrayView2<'_, F>, channels: usize) -> Array2<F> {
+    let (n_samples, positions) = (values.nrows(), values.ncols() / channels);
+    values
+        .into_shape((n_samples, channels, positions))
+        .expect("Columns hold `channels` blocks per sample")
+        .permuted_axes([0, 2, 1])
+        .as_standard_layout()
+        .into_owned()
+        .into_shape((n_samples * positions, channels))
+        .expect("Standard layout reshapes without copying")
+}
+
+/// 2D convolution over (batch, channels, height, width) images, giving (batch, `output_shape()`) outputs.
+/// The im2col rows are multiplied by the flattened kernels in a single matrix product.
+pub struct Conv2D<F: Float = f64> {
+    /// Kernels of shape (out_channels, in_channels, kernel_height, kernel_width).
+    pub weights: Array4<F>,
+    pub biases: Array1<F>,
+    pub weights_trainable: bool,
+    pub biases_trainable: bool,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dweights: Option<Array4<F>>,
+    pub dbiases: Option<Array1<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    geometry: Geometry,
+    columns: Option<Array2<F>>,
+    weights_id: ParamId,
+    biases_id: ParamId
+}
+
+impl<F: Float> Conv2D<F> {
+    /// `input_shape` is (channels, height, width); `padding` is added on both sides of each spatial axis.
+    pub fn new(
+        input_shape: (usize, usize, usize), 
+        out_channels: usize, 
+        kernel_size: (usize, usize), 
+        stride: (usize, usize), 
+        padding: (usize, usize), 
+        dilation: (usize, usize)
+    ) -> Result<Self> {
+        let geometry = Geometry::new(
+            input_shape, kernel_size, stride, dilation, (padding.0, padding.0), (padding.1, padding.1)
+        )?;
+        Ok(Conv2D::from_geometry(geometry, out_channels))
+    }
+
+    fn from_geometry(geometry: Geometry, out_channels: usize) -> Self {
+        let (kh, kw) = geometry.kernel;
+        let fan_in = geometry.patch_size();
+        let weights = Initializer::XavierUniform
+            .sample::<F>((out_channels, fan_in), fan_in, out_channels * kh * kw)
+            .into_shape((out_channels, geometry.channels, kh, kw))
+            .expect("Kernel matrix has out_channels * patch_size entries");
+
+        Conv2D {
+            weights,
+            biases: Array1::zeros(out_channels),
+            weights_trainable: true,
+            biases_trainable: true,
+            outputs: None,
+            dweights: None,
+            dbiases: None,
+            dinputs: None,
+            geometry,
+            columns: None,
+            weights_id: ParamId::unique(),
+            biases_id: ParamId::unique()
+        }
+    }
+
+    /// Shape (channels, height, width) of one output sample.
+    pub fn output_shape(&self) -> (usize, usize, usize) {
+        (self.biases.len(), self.geometry.out_height, self.geometry.out_width)
+    }
+
+    fn kernel_matrix(&self) -> Array2<F> {
+        let (out_channels, patch_size) = (self.biases.len(), self.geometry.patch_size());
+        self.weights
+            .as_standard_layout()
+            .into_owned()
+            .into_shape((out_channels, patch_size))
+            .expect("Standard layout reshapes without copying")
+    }
+}
+
+impl<F: Float> Module<F> for Conv2D<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let rows = self.geometry.image_rows(inputs)?;
+        let n_samples = rows.nrows();
+
+        let columns = self.geometry.im2col(rows.view());
+        let outputs = columns.dot(&self.kernel_matrix().t()) + &self.biases;
+        let (channels, height, width) = self.output_shape();
+        self.outputs = Some(reshape(
+            positions_to_channels(outputs, n_samples, self.geometry.output_positions()), 
+            &[n_samples, channels, height, width]
+        ));
+        self.columns = Some(columns);
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let columns = self.columns.as_ref().ok_or(NnError::CallOrder { missing: "inputs", call: "forward" })?;
+        let n_samples = columns.nrows() / self.geometry.output_positions();
+        let (channels, height, width) = self.output_shape();
+        check_shape(dvalues, &[n_samples, channels, height, width])?;
+
+        let dvalues = dvalues.to_shape((n_samples, channels * height * width)).expect("Element count is unchanged");
+        let dvalues = channels_to_positions(dvalues.view(), channels);
+        self.dweights = Some(
+            dvalues.t().dot(columns)
+                .into_shape(self.weights.raw_dim())
+                .expect("Kernel gradient has one entry per weight")
+        );
+        self.dbiases = Some(dvalues.sum_axis(Axis(0)));
+
+        let g = &self.geometry;
+        let dinputs = g.col2im(&dvalues.dot(&self.kernel_matrix()), n_samples);
+        self.dinputs = Some(reshape(dinputs, &[n_samples, g.channels, g.height, g.width]));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dweights = self.dweights.as_ref().ok_or(NnError::CallOrder { missing: "dweights", call: "backward" })?;
+        let dbiases = self.dbiases.as_ref().ok_or(NnError::CallOrder { missing: "dbiases", call: "backward" })?;
+
+        Ok(vec![
+            Param { 
+                id: self.weights_id, 
+                values: self.weights.view_mut().into_dyn(), 
+                grads: dweights.view().into_dyn(), 
+                trainable: self.weights_trainable 
+            },
+            Param { 
+                id: self.biases_id, 
+                values: self.biases.view_mut().into_dyn(), 
+                grads: dbiases.view().into_dyn(), 
+                trainable: self.biases_trainable 
+            }
+        ])
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.weights_trainable = trainable;
+        self.biases_trainable = trainable;
+    }
+}
+
+/// 1D convolution over (batch, channels, length) sequences, giving (batch, `output_shape()`) outputs.
+/// Runs as a `Conv2D` over height-1 images.
+pub struct Conv1D<F: Float = f64> {
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    conv: Conv2D<F>
+}
+
+impl<F: Float> Conv1D<F> {
+    /// `input_shape` is (channels, length); `padding` is added on both ends of the sequence.
+    pub fn new(
+        input_shape: (usize, usize), 
+        out_channels: usize, 
+        kernel_size: usize, 
+        stride: usize, 
+        padding: usize, 
+        dilation: usize
+    ) -> Result<Self> {
+        let (channels, length) = input_shape;
+        let geometry = Geometry::new(
+            (channels, 1, length), (1, kernel_size), (1, stride), (1, dilation), (0, 0), (padding, padding)
+        )?;
+        Ok(Conv1D { outputs: None, dinputs: None, conv: Conv2D::from_geometry(geometry, out_channels) })
+    }
+
+    /// Causal convolution: the sequence is padded on the left only, so output step `t` sees inputs up to `t`
+    /// and the output keeps the input length.
+    pub fn causal(input_shape: (usize, usize), out_channels: usize, kernel_size: usize, dilation: usize) -> Result<Self> {
+        let (channels, length) = input_shape;
+        let pad = kernel_size.saturating_sub(1) * dilation;
+        let geometry = Geometry::new(
+            (channels, 1, length), (1, kernel_size), (1, 1), (1, dilation), (0, 0), (pad, 0)
+        )?;
+        Ok(Conv1D { outputs: None, dinputs: None, conv: Conv2D::from_geometry(geometry, out_channels) })
+    }
+
+    /// Kernels of shape (out_channels, in_channels, kernel_size).
+    pub fn weights(&self) -> ArrayView3<'_, F> {
+        self.conv.weights.index_axis(Axis(2), 0)
+    }
+
+    pub fn weights_mut(&mut self) -> ArrayViewMut3<'_, F> {
+        self.conv.weights.index_axis_mut(Axis(2), 0)
+    }
+
+    pub fn biases(&self) -> &Array1<F> {
+        &self.conv.biases
+    }
+
+    /// Shape (channels, length) of one output sample.
+    pub fn output_shape(&self) -> (usize, usize) {
+        let (channels, _, length) = self.conv.output_shape();
+        (channels, length)
+    }
+}
+
+impl<F: Float> Module<F> for Conv1D<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let n_samples = to_rank::<F, Ix3>(inputs)?.len_of(Axis(0));
+        check_shape(inputs, &[n_samples, self.conv.geometry.channels, self.conv.geometry.width])?;
+
+        self.conv.forward(&inputs.clone().insert_axis(Axis(2)))?;
+        self.outputs = Some(self.conv.outputs()?.clone().remove_axis(Axis(2)));
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+        self.conv.backward(&dvalues.clone().insert_axis(Axis(2)))?;
+        self.dinputs = Some(self.conv.dinputs()?.clone().remove_axis(Axis(2)));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        self.conv.params()
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.conv.set_trainable(trainable);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{Array4, Ix4};
+
+    use super::*;
+    use crate::testing::{assert_gradients, random};
+
+    // Direct nested-loop convolution to compare the im2col path against.
+    fn naive_conv2d(conv: &Conv2D<f64>, inputs: &Array4<f64>, stride: (usize, usize), padding: (usize, usize), dilation: (usize, usize)) -> Array4<f64> {
+        let (n, channels, height, width) = inputs.dim();
+        let (out_channels, _, kh, kw) = conv.weights.dim();
+        let (_, oh, ow) = conv.output_shape();
+        Array4::from_shape_fn((n, out_channels, oh, ow), |(sample, out, y, x)| {
+            let mut total = conv.biases[out];
+            for channel in 0..channels {
+                for i in 0..kh {
+                    for j in 0..kw {
+                        let row = (y * stride.0 + i * dilation.0) as isize - padding.0 as isize;
+                        let column = (x * stride.1 + j * dilation.1) as isize - padding.1 as isize;
+                        if (0..height as isize).contains(&row) && (0..width as isize).contains(&column) {
+                            total += inputs[[sample, channel, row as usize, column as usize]] * conv.weights[[out, channel, i, j]];
+                        }
+                    }
+                }
+            }
+            total
+        })
+    }
+
+    #[test]
+    fn conv2d_matches_naive_convolution_and_gradients() {
+        for (stride, padding, dilation) in [((1, 1), (0, 0), (1, 1)), ((2, 1), (1, 2), (1, 2)), ((2, 2), (1, 1), (2, 1))] {
+            let mut conv = Conv2D::new((2, 6, 7), 3, (3, 2), stride, padding, dilation).unwrap();
+            conv.biases = Array1::from(vec![0.1, -0.2, 0.3]);
+            let inputs = random(&[2, 2, 6, 7], 1);
+
+            conv.forward(&inputs).unwrap();
+            let expected = naive_conv2d(&conv, &inputs.clone().into_dimensionality::<Ix4>().unwrap(), stride, padding, dilation);
+            assert!((conv.outputs().unwrap() - &expected.into_dyn()).iter().all(|d| d.abs() < 1e-12));
+
+            assert_gradients(&mut conv, &inputs);
+        }
+    }
+
+    #[test]
+    fn conv2d_rejects_bad_shapes() {
+        assert!(Conv2D::<f64>::new((1, 2, 2), 1, (3, 3), (1, 1), (0, 0), (1, 1)).is_err());
+
+        let mut conv = Conv2D::<f64>::new((2, 6, 7), 3, (3, 2), (1, 1), (0, 0), (1, 1)).unwrap();
+        assert!(matches!(conv.forward(&random(&[2, 84], 2)), Err(NnError::RankMismatch { expected: 4, found: 2 })));
+        assert!(matches!(conv.forward(&random(&[2, 3, 6, 7], 3)), Err(NnError::ShapeMismatch { .. })));
+    }
+
+    #[test]
+    fn conv1d_gradients() {
+        let inputs = random(&[3, 2, 10], 4);
+        assert_gradients(&mut Conv1D::new((2, 10), 3, 3, 2, 1, 2).unwrap(), &inputs);
+        assert_gradients(&mut Conv1D::causal((2, 10), 3, 3, 2).unwrap(), &inputs);
+    }
+
+    #[test]
+    fn causal_conv1d_only_sees_the_past() {
+        let mut conv = Conv1D::causal((2, 10), 3, 3, 2).unwrap();
+        assert_eq!(conv.output_shape(), (3, 10));
+
+        let inputs = random(&[2, 2, 10], 5);
+        conv.forward(&inputs).unwrap();
+        let before = conv.outputs().unwrap().clone();
+
+        // Naive dilated causal convolution: tap k looks (kernel_size - 1 - k) * dilation steps back.
+        let (weights, biases) = (conv.weights().to_owned(), conv.biases().clone());
+        for out in 0..3 {
+            for t in 0..10usize {
+                let mut total = biases[out];
+                for channel in 0..2 {
+                    for k in 0..3 {
+                        if let Some(source) = t.checked_sub((2 - k) * 2) {
+                            total += weights[[out, channel, k]] * inputs[[1, channel, source]];
+                        }
+                    }
+                }
+                assert!((total - before[[1, out, t]]).abs() < 1e-12);
+            }
+        }
+
+        let mut shifted = inputs.clone();
+        shifted[[0, 0, 7]] += 1.0;
+        shifted[[0, 1, 7]] -= 1.0;
+        conv.forward(&shifted).unwrap();
+        let after = conv.outputs().unwrap();
+        for out in 0..3 {
+            for t in 0..7 {
+                assert_eq!(before[[0, out, t]], after[[0, out, t]]);
+            }
+        }
+    }
+}
\ No newline at end of file
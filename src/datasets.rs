@@ -1,17 +1,18 @@
-#![allow(dead_code)]
-
 use ndarray::{Array1, Array2};
-use rand::thread_rng;
+use rand::Rng;
 use rand_distr::{Distribution, Normal};
-use crate::utils::linspace;
+use crate::{random::with_rng, utils::linspace};
 
 pub fn spiral_data(samples: usize, classes: usize) -> (Array2<f64>, Array1<usize>) {
+    with_rng(|rng| spiral_data_using(samples, classes, rng))
+}
+
+pub fn spiral_data_using<R: Rng + ?Sized>(samples: usize, classes: usize, rng: &mut R) -> (Array2<f64>, Array1<usize>) {
     let total = samples * classes;
     let mut x = Array2::<f64>::zeros((total, 2));
     let mut y = Array1::<usize>::zeros(total);
 
     let noise = Normal::new(0.0, 0.2).unwrap();
-    let mut rng = thread_rng();
 
     for class_number in 0..classes {
         let base_idx = class_number * samples;
@@ -24,7 +25,7 @@ pub fn spiral_data(samples: usize, classes: usize) -> (Array2<f64>, Array1<usize
             samples,
         )
         .into_iter()
-        .map(|v| v + noise.sample(&mut rng))
+        .map(|v| v + noise.sample(rng))
         .collect();
 
         for i in 0..samples {
@@ -32,7 +33,7 @@ pub fn spiral_data(samples: usize, classes: usize) -> (Array2<f64>, Array1<usize
             let theta = t[i] * 2.5;
             x[(idx, 0)] = r[i] * theta.sin();
             x[(idx, 1)] = r[i] * theta.cos();
-            y[idx] = class_number as usize;
+            y[idx] = class_number;
         }
     }
 
@@ -40,22 +41,25 @@ pub fn spiral_data(samples: usize, classes: usize) -> (Array2<f64>, Array1<usize
 }
 
 pub fn vertical_data(samples: usize, classes: usize) -> (Array2<f64>, Array1<usize>) {
+    with_rng(|rng| vertical_data_using(samples, classes, rng))
+}
+
+pub fn vertical_data_using<R: Rng + ?Sized>(samples: usize, classes: usize, rng: &mut R) -> (Array2<f64>, Array1<usize>) {
     let total = samples * classes;
     let mut x = Array2::<f64>::zeros((total, 2));
     let mut y = Array1::<usize>::zeros(total);
 
     let normal = Normal::new(0.0, 1.0).unwrap();
-    let mut rng = thread_rng();
 
     for class_number in 0..classes {
         let base = class_number * samples;
         for i in 0..samples {
             let idx = base + i;
-            let x0 = normal.sample(&mut rng) * 0.1 + (class_number as f64) / 3.0;
-            let x1 = normal.sample(&mut rng) * 0.1 + 0.5;
+            let x0 = normal.sample(rng) * 0.1 + (class_number as f64) / 3.0;
+            let x1 = normal.sample(rng) * 0.1 + 0.5;
             x[[idx, 0]] = x0;
             x[[idx, 1]] = x1;
-            y[idx] = class_number as usize;
+            y[idx] = class_number;
         }
     }
 
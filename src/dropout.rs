@@ -0,0 +1,142 @@
+use ndarray::{ArrayD, IxDyn};
+use ndarray_rand::RandomExt;
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::Bernoulli;
+
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    module::Module, 
+    random::with_rng, 
+    utils::check_shape
+};
+
+/// Inverted dropout: zeroes a `rate` fraction of activations while training and scales the
+/// survivors by `1 / (1 - rate)`, so inference is a plain pass-through.
+pub struct Dropout<F: Float = f64> {
+    pub training: bool,
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    // Private so it stays in [0, 1) as checked by `new`.
+    rate: f64,
+    mask: Option<ArrayD<F>>,
+    rng: Option<StdRng>
+}
+
+impl<F: Float> Dropout<F> {
+    pub fn new(rate: f64) -> Result<Self> {
+        if !(0.0..1.0).contains(&rate) {
+            return Err(NnError::InvalidHyperparameter(format!("dropout rate {} is not in [0, 1)", rate)));
+        }
+
+        Ok(Dropout {
+            training: true,
+            outputs: None,
+            dinputs: None,
+            rate,
+            mask: None,
+            rng: None
+        })
+    }
+
+    /// Same as `new`, drawing masks from its own generator seeded with `seed`.
+    pub fn with_seed(rate: f64, seed: u64) -> Result<Self> {
+        let mut dropout = Dropout::new(rate)?;
+        dropout.rng = Some(StdRng::seed_from_u64(seed));
+        Ok(dropout)
+    }
+
+    /// Fraction of activations zeroed while training.
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    fn sample_mask(&mut self, shape: IxDyn) -> ArrayD<F> {
+        let keep = 1.0 - self.rate;
+        let bernoulli = Bernoulli::new(keep).expect("Keep probability is in (0, 1]");
+        let mask = match self.rng.as_mut() {
+            Some(rng) => ArrayD::random_using(shape, bernoulli, rng),
+            None => with_rng(|rng| ArrayD::random_using(shape, bernoulli, rng))
+        };
+        let scale = F::cast(1.0 / keep);
+        mask.mapv(|kept| if kept { scale } else { F::zero() })
+    }
+}
+
+impl<F: Float> Module<F> for Dropout<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        if self.training && self.rate > 0.0 {
+            let mask = self.sample_mask(inputs.raw_dim());
+            self.outputs = Some(inputs * &mask);
+            self.mask = Some(mask);
+        }
+
+        else {
+            self.outputs = Some(inputs.clone());
+            self.mask = None;
+        }
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+
+        self.dinputs = Some(match &self.mask {
+            Some(mask) => dvalues * mask,
+            None => dvalues.clone()
+        });
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array2;
+
+    use super::*;
+
+    #[test]
+    fn masks_and_rescales_while_training() {
+        let inputs = Array2::<f64>::ones((200, 50)).into_dyn();
+        let mut dropout = Dropout::with_seed(0.3, 1).unwrap();
+        dropout.forward(&inputs).unwrap();
+        let outputs = dropout.outputs().unwrap().clone();
+
+        let zeroed = outputs.iter().filter(|&&v| v == 0.0).count() as f64 / inputs.len() as f64;
+        assert!((zeroed - 0.3).abs() < 0.03);
+        assert!((outputs.mean().unwrap() - 1.0).abs() < 0.05);
+
+        dropout.backward(&inputs).unwrap();
+        assert_eq!(dropout.dinputs().unwrap(), &outputs);
+    }
+
+    #[test]
+    fn passes_through_at_inference() {
+        let inputs = Array2::<f64>::from_elem((3, 4), 2.0).into_dyn();
+        let mut dropout = Dropout::new(0.5).unwrap();
+        dropout.set_training(false);
+        dropout.forward(&inputs).unwrap();
+        assert_eq!(dropout.outputs().unwrap(), &inputs);
+    }
+
+    #[test]
+    fn rejects_rates_outside_unit_interval() {
+        assert_eq!(Dropout::<f64>::new(0.25).unwrap().rate(), 0.25);
+        for rate in [1.0, 1.5, -0.1] {
+            assert!(matches!(Dropout::<f64>::new(rate), Err(NnError::InvalidHyperparameter(_))));
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,208 @@
+use ndarray::{s, Array2, Array3, ArrayD, Ix2};
+
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    initializers::Initializer, 
+    module::{Module, Param, ParamId}, 
+    utils::{check_shape, to_rank}
+};
+
+/// Lookup table mapping `usize` indices (token IDs, categories) to dense vectors.
+/// A batch of shape (batch, steps) becomes (batch, steps, embedding_dim), one vector per step.
+pub struct Embedding<F: Float = f64> {
+    /// Table of shape (vocab_size, embedding_dim).
+    pub weights: Array2<F>,
+    pub weights_trainable: bool,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dweights: Option<Array2<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    indices: Option<Array2<usize>>,
+    // Rows of `dweights` written by the last backward pass, cleared before the next one.
+    touched: Vec<usize>,
+    // One ID per row, so optimizer state is only ever created for rows that were looked up.
+    row_ids: Vec<ParamId>
+}
+
+impl<F: Float> Embedding<F> {
+    pub fn new(vocab_size: usize, embedding_dim: usize) -> Self {
+        Embedding::with_initializer(vocab_size, embedding_dim, Initializer::RandomNormal(0.1))
+    }
+
+    pub fn with_initializer(vocab_size: usize, embedding_dim: usize, init: Initializer) -> Self {
+        Embedding {
+            weights: init.initialize(vocab_size, embedding_dim),
+            weights_trainable: true,
+            outputs: None,
+            dweights: None,
+            dinputs: None,
+            indices: None,
+            touched: Vec::new(),
+            row_ids: (0..vocab_size).map(|_| ParamId::unique()).collect()
+        }
+    }
+
+    pub fn vocab_size(&self) -> usize {
+        self.weights.nrows()
+    }
+
+    pub fn embedding_dim(&self) -> usize {
+        self.weights.ncols()
+    }
+
+    /// Looks up every index of `indices`, which has shape (batch, steps).
+    pub fn forward_indices(&mut self, indices: &Array2<usize>) -> Result<()> {
+        if let Some(&index) = indices.iter().find(|&&index| index >= self.vocab_size()) {
+            return Err(NnError::InvalidIndex(format!("{} is outside a vocabulary of {}", index, self.vocab_size())));
+        }
+
+        let dim = self.embedding_dim();
+        let mut outputs = Array3::zeros((indices.nrows(), indices.ncols(), dim));
+        for ((sample, step), &index) in indices.indexed_iter() {
+            outputs.slice_mut(s![sample, step, ..]).assign(&self.weights.row(index));
+        }
+
+        self.outputs = Some(outputs.into_dyn());
+        self.indices = Some(indices.clone());
+        Ok(())
+    }
+
+    pub fn dweights(&self) -> Result<&Array2<F>> {
+        self.dweights.as_ref().ok_or(NnError::CallOrder { missing: "dweights", call: "backward" })
+    }
+}
+
+impl<F: Float> Module<F> for Embedding<F> {
+    /// Takes indices stored as floats so embeddings can head a `Sequential`; see `forward_indices`.
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let inputs = to_rank::<F, Ix2>(inputs)?;
+        let mut indices = Array2::zeros(inputs.dim());
+        for (index, &value) in indices.iter_mut().zip(&inputs) {
+            let value = value.as_f64();
+            if value < 0.0 || value.fract() != 0.0 {
+                return Err(NnError::InvalidIndex(format!("{} is not a non-negative integer", value)));
+            }
+            *index = value as usize;
+        }
+        self.forward_indices(&indices)
+    }
+
+    /// Scatter-adds `dvalues` into the looked-up rows of `dweights`; every other row stays zero.
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let indices = self.indices.as_ref().ok_or(NnError::CallOrder { missing: "inputs", call: "forward" })?;
+        check_shape(dvalues, &[indices.nrows(), indices.ncols(), self.weights.ncols()])?;
+
+        let dweights = self.dweights.get_or_insert_with(|| Array2::zeros(self.weights.raw_dim()));
+        for &row in &self.touched {
+            dweights.row_mut(row).fill(F::zero());
+        }
+        self.touched.clear();
+
+        for ((sample, step), &index) in indices.indexed_iter() {
+            let mut row = dweights.row_mut(index);
+            row += &dvalues.slice(s![sample, step, ..]);
+            self.touched.push(index);
+        }
+        self.touched.sort_unstable();
+        self.touched.dedup();
+
+        // Indices are not differentiable; downstream of an embedding there is nothing to propagate to.
+        self.dinputs = Some(Array2::zeros(indices.dim()).into_dyn());
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    /// One parameter per row looked up in the last batch, so optimizers update (and keep state for)
+    /// those rows only instead of walking the whole table.
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dweights = self.dweights.as_ref().ok_or(NnError::CallOrder { missing: "dweights", call: "backward" })?;
+        if self.row_ids.len() != self.weights.nrows() {
+            self.row_ids = (0..self.weights.nrows()).map(|_| ParamId::unique()).collect();
+        }
+
+        let mut rows = self.weights.outer_iter_mut();
+        let mut next = 0;
+        let mut params = Vec::with_capacity(self.touched.len());
+        for &row in &self.touched {
+            let values = rows.nth(row - next).expect("Touched rows are sorted and inside the table");
+            next = row + 1;
+            params.push(Param { 
+                id: self.row_ids[row], 
+                values: values.into_dyn(), 
+                grads: dweights.row(row).into_dyn(), 
+                trainable: self.weights_trainable 
+            });
+        }
+        Ok(params)
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.weights_trainable = trainable;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, ArrayD};
+
+    use super::*;
+    use crate::{
+        optimizers::{Adam, Optimizer, SGD}, 
+        testing::{assert_param_gradients, random}
+    };
+
+    fn lookup(embedding: &mut Embedding, indices: ArrayD<f64>) {
+        embedding.forward(&indices).unwrap();
+        let dvalues = random(embedding.outputs().unwrap().shape(), 3);
+        embedding.backward(&dvalues).unwrap();
+    }
+
+    #[test]
+    fn embedding_gradients() {
+        let mut embedding = Embedding::new(6, 4);
+        // Repeated indices check that gradients of the same row accumulate.
+        assert_param_gradients(&mut embedding, &array![[0.0, 3.0, 3.0], [5.0, 0.0, 1.0]].into_dyn());
+    }
+
+    #[test]
+    fn optimizers_only_update_looked_up_rows() {
+        let optimizers: Vec<Box<dyn Optimizer>> = vec![Box::new(SGD::new(0.1, 0.0, 0.9)), Box::new(Adam::new())];
+        for mut optimizer in optimizers {
+            let mut embedding = Embedding::new(5, 3);
+            let initial = embedding.weights.clone();
+
+            lookup(&mut embedding, array![[0.0, 2.0], [2.0, 0.0]].into_dyn());
+            optimizer.update_params(&mut embedding).unwrap();
+            for row in 0..5 {
+                assert_eq!(embedding.weights.row(row) == initial.row(row), ![0, 2].contains(&row), "row {}", row);
+            }
+
+            // Momentum from the first step must not keep moving rows missing from the second batch.
+            let first_step = embedding.weights.clone();
+            lookup(&mut embedding, array![[1.0, 1.0]].into_dyn());
+            optimizer.update_params(&mut embedding).unwrap();
+            for row in [0, 2, 3, 4] {
+                assert_eq!(embedding.weights.row(row), first_step.row(row), "row {}", row);
+            }
+            assert_ne!(embedding.weights.row(1), first_step.row(1));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_indices() {
+        let mut embedding: Embedding = Embedding::new(4, 2);
+        assert!(matches!(embedding.forward(&array![[1.0, 4.0]].into_dyn()), Err(NnError::InvalidIndex(_))));
+        assert!(matches!(embedding.forward(&array![[1.5]].into_dyn()), Err(NnError::InvalidIndex(_))));
+        assert!(matches!(embedding.forward(&array![[-1.0]].into_dyn()), Err(NnError::InvalidIndex(_))));
+        assert!(matches!(embedding.params(), Err(NnError::CallOrder { .. })));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,41 @@
+use std::fmt;
+
+/// Errors returned by the fallible layer, loss, optimizer and model APIs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NnError {
+    /// A value was read before the call that produces it, e.g. `dweights` before `backward`.
+    CallOrder { missing: &'static str, call: &'static str },
+    ShapeMismatch { expected: Vec<usize>, found: Vec<usize> },
+    /// An array had the wrong number of axes, e.g. a 2-D batch fed to a layer expecting NCHW images.
+    RankMismatch { expected: usize, found: usize },
+    InvalidHyperparameter(String),
+    InvalidLabel(String),
+    /// A lookup index was negative, fractional or outside the table, e.g. an unknown token ID.
+    InvalidIndex(String),
+    /// Values became NaN or infinite, usually because training diverged.
+    NonFinite(String),
+    /// A batch had no samples, or a sequence had no time steps.
+    EmptyBatch
+}
+
+impl fmt::Display for NnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NnError::CallOrder { missing, call } => 
+                write!(f, "{} not yet set. Make sure to call `{}` first.", missing, call),
+            NnError::ShapeMismatch { expected, found } => 
+                write!(f, "Shape mismatch: expected {:?}, found {:?}.", expected, found),
+            NnError::RankMismatch { expected, found } => 
+                write!(f, "Rank mismatch: expected {} axes, found {}.", expected, found),
+            NnError::InvalidHyperparameter(msg) => write!(f, "Invalid hyperparameter: {}.", msg),
+            NnError::InvalidLabel(msg) => write!(f, "Invalid label: {}.", msg),
+            NnError::InvalidIndex(msg) => write!(f, "Invalid index: {}.", msg),
+            NnError::NonFinite(msg) => write!(f, "Non-finite values: {}.", msg),
+            NnError::EmptyBatch => write!(f, "Batch is empty.")
+        }
+    }
+}
+
+impl std::error::Error for NnError {}
+
+pub type Result<T> = std::result::Result<T, NnError>;
\ No newline at end of file
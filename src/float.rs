@@ -0,0 +1,88 @@
+use std::{
+    fmt::{Debug, Display},
+    iter::Sum,
+    ops::{AddAssign, DivAssign, MulAssign, SubAssign}
+};
+
+use ndarray::{LinalgScalar, ScalarOperand};
+use num_traits::FromPrimitive;
+
+/// Element type of every layer, activation, loss and optimizer; implemented for `f32` and `f64`.
+pub trait Float:
+    num_traits::Float 
+    + FromPrimitive 
+    + LinalgScalar 
+    + ScalarOperand 
+    + AddAssign 
+    + SubAssign 
+    + MulAssign 
+    + DivAssign 
+    + Sum 
+    + Debug 
+    + Display 
+    + Send 
+    + Sync 
+    + 'static 
+{
+    /// Converts an `f64` hyperparameter or constant into this precision.
+    fn cast(value: f64) -> Self;
+
+    fn as_f64(self) -> f64;
+}
+
+impl Float for f32 {
+    fn cast(value: f64) -> Self {
+        value as f32
+    }
+
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Float for f64 {
+    fn cast(value: f64) -> Self {
+        value
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        activations::ReLU, 
+        datasets::spiral_data, 
+        layer::Layer, 
+        loss_functions::SoftmaxCategoricalCrossEntropy, 
+        model::{Model, Sequential}, 
+        normalization::LayerNorm, 
+        optimizers::Adam, 
+        testing::{assert_gradients_with, random}
+    };
+
+    #[test]
+    fn f32_models_train() {
+        let (x, y) = spiral_data(30, 3);
+        let x = x.mapv(|v| v as f32);
+
+        let mut model = Sequential::<f32>::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(Adam::new()));
+        model.add(Layer::new(2, 16));
+        model.add(ReLU::new());
+        model.add(Layer::new(16, 3));
+
+        let history = model.fit(&x, &y, 20, 32, None).unwrap();
+        assert!(history.loss.iter().all(|loss| loss.is_finite()));
+        assert!(history.loss[history.loss.len() - 1] < history.loss[0]);
+        assert_eq!(model.predict(&x).unwrap().dim(), (90, 3));
+    }
+
+    #[test]
+    fn f32_gradients() {
+        let inputs = random(&[3, 4], 1).mapv(|v| v as f32);
+        assert_gradients_with(&mut Layer::<f32>::new(4, 3), &inputs, 1e-2, 1e-2);
+        assert_gradients_with(&mut LayerNorm::<f32>::new(4), &inputs, 1e-2, 1e-2);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,377 @@
+use ndarray::{concatenate, Array1, Array2, ArrayD, ArrayView, Axis, IxDyn, Slice};
+
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    loss_functions::Loss, 
+    model::{padding_mask, Model}, 
+    module::Module, 
+    optimizers::Optimizer
+};
+
+/// Handle to a node of a `Graph`, returned when the node is added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+enum Op<F: Float> {
+    Input,
+    Module(Box<dyn Module<F>>),
+    Sum,
+    Concat(Axis)
+}
+
+struct Node<F: Float> {
+    op: Op<F>,
+    inputs: Vec<NodeId>,
+    // Outputs of input, sum and concat nodes; modules keep their own.
+    outputs: Option<ArrayD<F>>
+}
+
+impl<F: Float> Node<F> {
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        match &self.op {
+            Op::Module(module) => module.outputs(),
+            _ => self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+        }
+    }
+}
+
+// Adds `grad` to the gradient collected so far for `node`, so every consumer of a node contributes to it.
+fn accumulate<F: Float>(grads: &mut [Option<ArrayD<F>>], node: NodeId, grad: ArrayView<F, IxDyn>) {
+    match &mut grads[node.0] {
+        Some(total) => *total += &grad,
+        slot => *slot = Some(grad.to_owned())
+    }
+}
+
+/// A model whose modules form a directed acyclic graph rooted at a single input: node outputs can be
+/// summed (skip connections), concatenated or fed to several downstream nodes. Nodes only take inputs
+/// from nodes added before them, so insertion order is a valid evaluation order.
+pub struct Graph<F: Float = f64> {
+    pub loss: Box<dyn Loss<F>>,
+    pub optimizer: Box<dyn Optimizer<F>>,
+    /// Token ID marking padded steps of (batch, time) inputs; see `Sequential::padding_token`.
+    pub padding_token: Option<usize>,
+
+    nodes: Vec<Node<F>>,
+    output: Option<NodeId>,
+    // Nodes the last backward pass reached from the output; only their modules have fresh gradients.
+    reached: Vec<bool>
+}
+
+impl<F: Float> Graph<F> {
+    pub fn new(loss: Box<dyn Loss<F>>, optimizer: Box<dyn Optimizer<F>>) -> Self {
+        Graph {
+            loss,
+            optimizer,
+            padding_token: None,
+            nodes: vec![Node { op: Op::Input, inputs: Vec::new(), outputs: None }],
+            output: None,
+            reached: Vec::new()
+        }
+    }
+
+    /// The node holding the batch passed to the model.
+    pub fn input(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// Adds `module` fed by the outputs of `input`.
+    pub fn add<M: Module<F> + 'static>(&mut self, module: M, input: NodeId) -> Result<NodeId> {
+        self.push(Op::Module(Box::new(module)), vec![input])
+    }
+
+    /// Adds a node producing the element-wise sum of `inputs`, which must all have the same shape.
+    pub fn sum(&mut self, inputs: &[NodeId]) -> Result<NodeId> {
+        if inputs.len() < 2 {
+            return Err(NnError::InvalidHyperparameter("sum needs at least two input nodes".to_string()));
+        }
+        self.push(Op::Sum, inputs.to_vec())
+    }
+
+    /// Adds a node concatenating `inputs` along `axis`, e.g. axis 1 for the features of (batch, features)
+    /// outputs or the channels of NCHW images.
+    pub fn concat(&mut self, inputs: &[NodeId], axis: usize) -> Result<NodeId> {
+        if inputs.len() < 2 {
+            return Err(NnError::InvalidHyperparameter("concat needs at least two input nodes".to_string()));
+        }
+        if axis == 0 {
+            return Err(NnError::InvalidHyperparameter("cannot concatenate along the batch axis".to_string()));
+        }
+        self.push(Op::Concat(Axis(axis)), inputs.to_vec())
+    }
+
+    /// Makes `node` the one fed to the loss; defaults to the last node added.
+    pub fn set_output(&mut self, node: NodeId) -> Result<()> {
+        self.check_node(node)?;
+        self.output = Some(node);
+        Ok(())
+    }
+
+    /// Outputs of `node` from the last forward pass.
+    pub fn node_outputs(&self, node: NodeId) -> Result<&ArrayD<F>> {
+        self.check_node(node)?;
+        self.nodes[node.0].outputs()
+    }
+
+    /// The module at `node`, e.g. to inspect its weights after training.
+    pub fn module_mut(&mut self, node: NodeId) -> Result<&mut dyn Module<F>> {
+        self.check_node(node)?;
+        match &mut self.nodes[node.0].op {
+            Op::Module(module) => Ok(module.as_mut()),
+            _ => Err(NnError::InvalidIndex(format!("node {} is not a module", node.0)))
+        }
+    }
+
+    fn check_node(&self, node: NodeId) -> Result<()> {
+        if node.0 >= self.nodes.len() {
+            return Err(NnError::InvalidIndex(format!("node {} is not in a graph of {} nodes", node.0, self.nodes.len())));
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, op: Op<F>, inputs: Vec<NodeId>) -> Result<NodeId> {
+        for &input in inputs.iter() {
+            self.check_node(input)?;
+        }
+        self.nodes.push(Node { op, inputs, outputs: None });
+        Ok(NodeId(self.nodes.len() - 1))
+    }
+
+    fn output_node(&self) -> Result<NodeId> {
+        if self.nodes.len() < 2 {
+            return Err(NnError::CallOrder { missing: "layers", call: "add" });
+        }
+        Ok(self.output.unwrap_or(NodeId(self.nodes.len() - 1)))
+    }
+
+    fn forward_nodes(&mut self, inputs: &ArrayD<F>) -> Result<NodeId> {
+        let output = self.output_node()?;
+
+        let mask = padding_mask(inputs, self.padding_token)?;
+        for node in self.nodes.iter_mut() {
+            if let Op::Module(module) = &mut node.op {
+                module.set_padding_mask(mask.as_ref());
+            }
+        }
+
+        self.nodes[0].outputs = Some(inputs.clone());
+        for i in 1..self.nodes.len() {
+            let (done, rest) = self.nodes.split_at_mut(i);
+            let node = &mut rest[0];
+            let sources = node.inputs.iter().map(|input| done[input.0].outputs()).collect::<Result<Vec<_>>>()?;
+
+            match &mut node.op {
+                Op::Input => {},
+                Op::Module(module) => module.forward(sources[0])?,
+                Op::Sum => {
+                    let mut total = sources[0].clone();
+                    for source in sources[1..].iter() {
+                        if source.shape() != total.shape() {
+                            return Err(NnError::ShapeMismatch { expected: total.shape().to_vec(), found: source.shape().to_vec() });
+                        }
+                        total += *source;
+                    }
+                    node.outputs = Some(total);
+                },
+                Op::Concat(axis) => {
+                    let first = sources[0];
+                    if first.ndim() <= axis.index() {
+                        return Err(NnError::RankMismatch { expected: axis.index() + 1, found: first.ndim() });
+                    }
+                    for source in sources[1..].iter() {
+                        let mut expected = first.shape().to_vec();
+                        if source.ndim() == expected.len() {
+                            expected[axis.index()] = source.len_of(*axis);
+                        }
+                        if source.shape() != expected.as_slice() {
+                            return Err(NnError::ShapeMismatch { expected, found: source.shape().to_vec() });
+                        }
+                    }
+                    let views: Vec<_> = sources.iter().map(|source| source.view()).collect();
+                    node.outputs = Some(concatenate(*axis, &views).expect("Shapes agree off the concatenation axis"));
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+impl<F: Float> Model<F> for Graph<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>, y_true: &Array1<usize>) -> Result<F> {
+        let output = self.forward_nodes(inputs)?;
+        self.loss.forward(self.nodes[output.0].outputs()?, y_true)
+    }
+
+    /// Propagates the loss gradient back through the graph in reverse insertion order, summing the
+    /// gradients of nodes with several consumers. Nodes that do not lead to the output, such as side
+    /// branches or nodes after the one given to `set_output`, are skipped and left alone by `update_params`.
+    fn backward(&mut self, y_true: &Array1<usize>) -> Result<()> {
+        let output = self.output_node()?;
+        self.loss.backward(y_true)?;
+
+        let mut grads: Vec<Option<ArrayD<F>>> = vec![None; self.nodes.len()];
+        grads[output.0] = Some(self.loss.dinputs()?.clone());
+        self.reached = vec![false; self.nodes.len()];
+
+        for i in (1..self.nodes.len()).rev() {
+            let grad = match grads[i].take() {
+                Some(grad) => grad,
+                None => continue
+            };
+            self.reached[i] = true;
+            let (before, rest) = self.nodes.split_at_mut(i);
+            let node = &mut rest[0];
+
+            match &mut node.op {
+                Op::Input => {},
+                Op::Module(module) => {
+                    module.backward(&grad)?;
+                    accumulate(&mut grads, node.inputs[0], module.dinputs()?.view());
+                },
+                Op::Sum => {
+                    for &input in node.inputs.iter() {
+                        accumulate(&mut grads, input, grad.view());
+                    }
+                },
+                Op::Concat(axis) => {
+                    let mut offset = 0;
+                    for &input in node.inputs.iter() {
+                        let width = before[input.0].outputs()?.len_of(*axis);
+                        accumulate(&mut grads, input, grad.slice_axis(*axis, Slice::from(offset..offset + width)));
+                        offset += width;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn update_params(&mut self) -> Result<()> {
+        if self.reached.is_empty() {
+            return Err(NnError::CallOrder { missing: "gradients", call: "backward" });
+        }
+
+        self.optimizer.pre_update_params();
+        for (node, &reached) in self.nodes.iter_mut().zip(&self.reached) {
+            if let Op::Module(module) = &mut node.op {
+                if reached {
+                    self.optimizer.update_params(module.as_mut())?;
+                }
+            }
+        }
+        self.optimizer.post_update_params();
+        Ok(())
+    }
+
+    fn set_training(&mut self, training: bool) {
+        for node in self.nodes.iter_mut() {
+            if let Op::Module(module) = &mut node.op {
+                module.set_training(training);
+            }
+        }
+    }
+
+    fn outputs(&self) -> Result<&Array2<F>> {
+        self.loss.outputs()
+    }
+
+    fn predictions(&mut self, inputs: &ArrayD<F>) -> Result<Array2<F>> {
+        let output = self.forward_nodes(inputs)?;
+        self.loss.predictions(self.nodes[output.0].outputs()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array2;
+
+    use super::*;
+    use crate::{
+        datasets::spiral_data, 
+        layer::Layer, 
+        loss_functions::SoftmaxCategoricalCrossEntropy, 
+        optimizers::SGD
+    };
+
+    fn graph() -> Graph {
+        Graph::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(SGD::new(1.0, 0.0, 0.0)))
+    }
+
+    #[test]
+    fn gradients_through_sum_and_concat() {
+        let (x, y) = spiral_data(4, 3);
+        let x = x.into_dyn();
+        let mut graph = graph();
+        let a = graph.add(Layer::new(2, 4), graph.input()).unwrap();
+        let b = graph.add(Layer::new(2, 4), graph.input()).unwrap();
+        let sum = graph.sum(&[a, b]).unwrap();
+        let joined = graph.concat(&[sum, a], 1).unwrap();
+        graph.add(Layer::new(8, 3), joined).unwrap();
+
+        graph.forward(&x, &y).unwrap();
+        graph.backward(&y).unwrap();
+        let analytic = graph.module_mut(a).unwrap().params().unwrap()[0].grads.to_owned();
+
+        // Node `a` feeds both the sum and the concat, so its gradient needs both contributions.
+        let step = 1e-6;
+        for index in 0..analytic.len() {
+            let mut loss_at = |delta: f64| {
+                let mut params = graph.module_mut(a).unwrap().params().unwrap();
+                *params[0].values.iter_mut().nth(index).unwrap() += delta;
+                drop(params);
+                graph.forward(&x, &y).unwrap()
+            };
+            let plus = loss_at(step);
+            let minus = loss_at(-2.0 * step);
+            loss_at(step);
+
+            let numeric = (plus - minus) / (2.0 * step);
+            let analytic = analytic.iter().nth(index).unwrap();
+            assert!((analytic - numeric).abs() < 1e-6 * (analytic.abs() + numeric.abs()).max(1e-3));
+        }
+    }
+
+    #[test]
+    fn nodes_past_the_output_are_not_updated() {
+        let (x, y) = spiral_data(10, 3);
+        let mut graph = graph();
+        let first = graph.add(Layer::new(2, 3), graph.input()).unwrap();
+        let second = graph.add(Layer::new(3, 3), first).unwrap();
+        graph.set_output(first).unwrap();
+
+        graph.fit(&x, &y, 2, 8, None).unwrap();
+        assert!(graph.module_mut(first).unwrap().params().is_ok());
+        // The second layer never got a gradient, so the optimizer must not have asked for its parameters.
+        assert!(matches!(graph.module_mut(second).unwrap().params(), Err(NnError::CallOrder { .. })));
+    }
+
+    #[test]
+    fn side_branches_are_not_updated() {
+        let (x, y) = spiral_data(10, 3);
+        let mut graph = graph();
+        let side = graph.add(Layer::new(2, 5), graph.input()).unwrap();
+        let main = graph.add(Layer::new(2, 3), graph.input()).unwrap();
+        graph.set_output(main).unwrap();
+
+        let x_dyn = x.clone().into_dyn();
+        graph.forward(&x_dyn, &y).unwrap();
+        let side_before = graph.node_outputs(side).unwrap().clone();
+        graph.fit(&x, &y, 2, 8, None).unwrap();
+        graph.forward(&x_dyn, &y).unwrap();
+        assert_eq!(graph.node_outputs(side).unwrap(), &side_before);
+    }
+
+    #[test]
+    fn rejects_updates_before_backward_and_mismatched_sums() {
+        let mut graph = graph();
+        let a = graph.add(Layer::new(2, 3), graph.input()).unwrap();
+        let b = graph.add(Layer::new(2, 4), graph.input()).unwrap();
+        assert!(matches!(graph.update_params(), Err(NnError::CallOrder { .. })));
+
+        graph.sum(&[a, b]).unwrap();
+        let x = Array2::<f64>::zeros((2, 2)).into_dyn();
+        assert!(matches!(graph.forward(&x, &Array1::zeros(2)), Err(NnError::ShapeMismatch { .. })));
+        assert!(matches!(graph.concat(&[a, b], 0), Err(NnError::InvalidHyperparameter(_))));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,159 @@
+use ndarray::{Array1, Array2};
+use ndarray_rand::RandomExt;
+use rand::Rng;
+use rand_distr::{Normal, StandardNormal, Uniform};
+
+use crate::{float::Float, random::with_rng};
+
+/// Weight and bias initialization schemes, scaled by the fan-in/fan-out of the layer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Initializer {
+    /// `std * N(0, 1)`; `RandomNormal(0.1)` is the `Layer::new` default.
+    RandomNormal(f64),
+    XavierUniform,
+    XavierNormal,
+    HeUniform,
+    HeNormal,
+    LeCunUniform,
+    LeCunNormal,
+    /// A (semi-)orthogonal matrix scaled by the given gain.
+    Orthogonal(f64),
+    Constant(f64),
+    Zeros
+}
+
+impl Initializer {
+    /// A `(fan_in, fan_out)` weight matrix.
+    pub fn initialize<F: Float>(&self, fan_in: usize, fan_out: usize) -> Array2<F> {
+        self.sample((fan_in, fan_out), fan_in, fan_out)
+    }
+
+    /// A bias vector of length `n_neurons` for a layer with `fan_in` inputs.
+    pub fn initialize_bias<F: Float>(&self, fan_in: usize, n_neurons: usize) -> Array1<F> {
+        self.sample((1, n_neurons), fan_in, n_neurons).row(0).to_owned()
+    }
+
+    /// A `shape` matrix with explicit fans, for layers whose fans differ from their matrix dimensions.
+    pub fn sample<F: Float>(&self, shape: (usize, usize), fan_in: usize, fan_out: usize) -> Array2<F> {
+        with_rng(|rng| self.sample_using(shape, fan_in, fan_out, rng))
+    }
+
+    /// Same as `sample`, drawing from `rng` instead of the crate-wide generator.
+    pub fn sample_using<F: Float, R: Rng + ?Sized>(
+        &self, 
+        shape: (usize, usize), 
+        fan_in: usize, 
+        fan_out: usize, 
+        rng: &mut R
+    ) -> Array2<F> {
+        if shape.0 * shape.1 == 0 {
+            return Array2::zeros(shape);
+        }
+
+        let (fan_in, fan_out) = (fan_in.max(1) as f64, fan_out.max(1) as f64);
+        let values: Array2<f64> = match *self {
+            Initializer::RandomNormal(std) => std * Array2::random_using(shape, StandardNormal, rng),
+            Initializer::XavierUniform => uniform(shape, (6.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::XavierNormal => normal(shape, (2.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::HeUniform => uniform(shape, (6.0 / fan_in).sqrt(), rng),
+            Initializer::HeNormal => normal(shape, (2.0 / fan_in).sqrt(), rng),
+            Initializer::LeCunUniform => uniform(shape, (3.0 / fan_in).sqrt(), rng),
+            Initializer::LeCunNormal => normal(shape, (1.0 / fan_in).sqrt(), rng),
+            Initializer::Orthogonal(gain) => gain * orthogonal(shape, rng),
+            Initializer::Constant(value) => Array2::from_elem(shape, value),
+            Initializer::Zeros => Array2::zeros(shape)
+        };
+
+        values.mapv(F::cast)
+    }
+}
+
+fn uniform<R: Rng + ?Sized>(shape: (usize, usize), limit: f64, rng: &mut R) -> Array2<f64> {
+    Array2::random_using(shape, Uniform::new_inclusive(-limit, limit), rng)
+}
+
+fn normal<R: Rng + ?Sized>(shape: (usize, usize), std: f64, rng: &mut R) -> Array2<f64> {
+    Array2::random_using(shape, Normal::new(0.0, std).expect("Standard deviation must be finite"), rng)
+}
+
+// Modified Gram-Schmidt over the longer dimension, so either the rows or the columns are orthonormal.
+fn orthogonal<R: Rng + ?Sized>(shape: (usize, usize), rng: &mut R) -> Array2<f64> {
+    let transpose = shape.0 < shape.1;
+    let (n, m) = if transpose { (shape.1, shape.0) } else { shape };
+    let mut q = Array2::<f64>::random_using((n, m), StandardNormal, rng);
+
+    for j in 0..m {
+        for k in 0..j {
+            let basis = q.column(k).to_owned();
+            let projection = q.column(j).dot(&basis);
+            q.column_mut(j).scaled_add(-projection, &basis);
+        }
+        let norm = q.column(j).dot(&q.column(j)).sqrt();
+        q.column_mut(j).mapv_inplace(|v| v / norm);
+    }
+
+    if transpose { q.reversed_axes() } else { q }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array2;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn sample(init: Initializer, shape: (usize, usize)) -> Array2<f64> {
+        init.sample_using(shape, shape.0, shape.1, &mut StdRng::seed_from_u64(1))
+    }
+
+    #[test]
+    fn uniform_limits_follow_the_fans() {
+        let (fan_in, fan_out) = (200f64, 100f64);
+        let cases = [
+            (Initializer::XavierUniform, (6.0 / (fan_in + fan_out)).sqrt()),
+            (Initializer::HeUniform, (6.0 / fan_in).sqrt()),
+            (Initializer::LeCunUniform, (3.0 / fan_in).sqrt())
+        ];
+        for (init, limit) in cases {
+            let values = sample(init, (200, 100));
+            let max = values.iter().fold(0.0f64, |max, v| max.max(v.abs()));
+            assert!(max <= limit && max > 0.99 * limit, "{:?}: max {} limit {}", init, max, limit);
+            // U(-a, a) has standard deviation a / sqrt(3).
+            assert!((values.std(0.0) / (limit / 3f64.sqrt()) - 1.0).abs() < 0.03, "{:?}", init);
+        }
+    }
+
+    #[test]
+    fn normal_deviations_follow_the_fans() {
+        let (fan_in, fan_out) = (200f64, 100f64);
+        let cases = [
+            (Initializer::XavierNormal, (2.0 / (fan_in + fan_out)).sqrt()),
+            (Initializer::HeNormal, (2.0 / fan_in).sqrt()),
+            (Initializer::LeCunNormal, (1.0 / fan_in).sqrt())
+        ];
+        for (init, expected) in cases {
+            let values = sample(init, (200, 100));
+            assert!((values.std(0.0) / expected - 1.0).abs() < 0.03, "{:?}: std {} expected {}", init, values.std(0.0), expected);
+            assert!(values.mean().unwrap().abs() < 0.03 * expected, "{:?}", init);
+        }
+    }
+
+    #[test]
+    fn orthogonal_rows_or_columns_are_orthonormal() {
+        for shape in [(3, 7), (7, 3), (5, 5)] {
+            let values = sample(Initializer::Orthogonal(2.0), shape) / 2.0;
+            // The Gram matrix over the shorter dimension is the identity: W Wᵀ when wide, Wᵀ W when tall.
+            let gram = if shape.0 <= shape.1 { values.dot(&values.t()) } else { values.t().dot(&values) };
+            let identity = Array2::<f64>::eye(shape.0.min(shape.1));
+            assert!((&gram - &identity).iter().all(|diff| diff.abs() < 1e-12), "{:?}", shape);
+        }
+    }
+
+    #[test]
+    fn constant_zero_and_empty_shapes() {
+        assert!(sample(Initializer::Constant(0.5), (2, 3)).iter().all(|&v| v == 0.5));
+        assert!(sample(Initializer::Zeros, (2, 3)).iter().all(|&v| v == 0.0));
+        assert_eq!(sample(Initializer::HeNormal, (0, 3)).dim(), (0, 3));
+        assert_eq!(Initializer::XavierUniform.initialize_bias::<f64>(4, 6).len(), 6);
+    }
+}
\ No newline at end of file
@@ -1,88 +1,266 @@
-#![allow(dead_code)]
+use ndarray::{Array1, Array2, ArrayD, Axis};
 
-use ndarray::{Array1, Array2, Axis};
-use ndarray_rand::RandomExt;
-use rand_distr::StandardNormal;
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    initializers::Initializer, 
+    module::{Module, Param, ParamId}, 
+    utils::{check_shape, reshape, to_rows}
+};
 
-pub struct Layer {
-    pub weights: Array2<f64>,
-    pub biases: Array1<f64>,
-    pub outputs: Array2<f64>,
+/// Fully connected layer over the last axis: (batch, ..., n_inputs) inputs give (batch, ..., n_neurons) outputs.
+pub struct Layer<F: Float = f64> {
+    /// Of shape (n_inputs, n_neurons); (0, n_neurons) until a lazy layer sees its first input.
+    pub weights: Array2<F>,
+    /// `None` for a layer built `without_bias`.
+    pub biases: Option<Array1<F>>,
+    pub weights_trainable: bool,
+    pub biases_trainable: bool,
+    pub outputs: Option<ArrayD<F>>,
 
-    pub inputs: Option<Array2<f64>>,
-    pub dweights: Option<Array2<f64>>,
-    pub dbiases: Option<Array1<f64>>,
-    pub dinputs: Option<Array2<f64>>,
+    pub inputs: Option<ArrayD<F>>,
+    pub dweights: Option<Array2<F>>,
+    pub dbiases: Option<Array1<F>>,
+    pub dinputs: Option<ArrayD<F>>,
 
-    pub weight_momentums: Option<Array2<f64>>,
-    pub bias_momentums: Option<Array1<f64>>,
-
-    pub weight_cache: Option<Array2<f64>>,
-    pub bias_cache: Option<Array1<f64>>
+    weights_id: ParamId,
+    biases_id: ParamId,
+    // Initializers of a lazy layer whose weights are not yet built.
+    pending: Option<(Initializer, Initializer)>
 }
 
-impl Layer {
-    pub fn new(n_inputs: usize, n_neurons: usize, batch_size: usize) -> Self {
-        let weights = 0.1 * Array2::random((n_inputs, n_neurons), StandardNormal);
-        let biases = Array1::zeros(n_neurons);
-        let outputs = Array2::zeros((batch_size, n_neurons));
+impl<F: Float> Layer<F> {
+    pub fn new(n_inputs: usize, n_neurons: usize) -> Self {
+        Layer::with_initializers(n_inputs, n_neurons, Initializer::RandomNormal(0.1), Initializer::Zeros)
+    }
+
+    pub fn with_initializers(n_inputs: usize, n_neurons: usize, weight_init: Initializer, bias_init: Initializer) -> Self {
+        let mut layer = Layer::lazy_with_initializers(n_neurons, weight_init, bias_init);
+        layer.build(n_inputs);
+        layer
+    }
+
+    /// A layer whose `n_inputs` is taken from the last axis of its first input, either on the first
+    /// `forward` call or when the model is built with `Model::build`.
+    pub fn lazy(n_neurons: usize) -> Self {
+        Layer::lazy_with_initializers(n_neurons, Initializer::RandomNormal(0.1), Initializer::Zeros)
+    }
 
+    /// Same as `lazy`, with the given initializers applied once `n_inputs` is known.
+    pub fn lazy_with_initializers(n_neurons: usize, weight_init: Initializer, bias_init: Initializer) -> Self {
         Layer { 
-            weights, 
-            biases, 
-            outputs, 
+            weights: Array2::zeros((0, n_neurons)), 
+            biases: Some(Array1::zeros(n_neurons)), 
+            weights_trainable: true, 
+            biases_trainable: true, 
+            outputs: None, 
             inputs: None, 
             dweights: None, 
             dbiases: None, 
             dinputs: None,
-            weight_momentums: None,
-            bias_momentums: None,
-            weight_cache: None,
-            bias_cache: None
+            weights_id: ParamId::unique(),
+            biases_id: ParamId::unique(),
+            pending: Some((weight_init, bias_init))
+        }
+    }
+
+    /// Drops the biases, so outputs are a plain `inputs · weights`.
+    pub fn without_bias(mut self) -> Self {
+        self.biases = None;
+        self
+    }
+
+    /// Whether the weights exist, i.e. the layer is not lazy or has already seen an input.
+    pub fn is_built(&self) -> bool {
+        self.pending.is_none()
+    }
+
+    fn build(&mut self, n_inputs: usize) {
+        if let Some((weight_init, bias_init)) = self.pending.take() {
+            let n_neurons = self.weights.ncols();
+            self.weights = weight_init.initialize(n_inputs, n_neurons);
+            if self.biases.is_some() {
+                self.biases = Some(bias_init.initialize_bias(n_inputs, n_neurons));
+            }
         }
     }
 
-    pub fn forward(&mut self, inputs: &Array2<f64>) {
+    pub fn inputs(&self) -> Result<&ArrayD<F>> {
+        self.inputs.as_ref().ok_or(NnError::CallOrder { missing: "inputs", call: "forward" })
+    }
+
+    pub fn dweights(&self) -> Result<&Array2<F>> {
+        self.dweights.as_ref().ok_or(NnError::CallOrder { missing: "dweights", call: "backward" })
+    }
+
+    pub fn dbiases(&self) -> Result<&Array1<F>> {
+        self.dbiases.as_ref().ok_or(NnError::CallOrder { missing: "dbiases", call: "backward" })
+    }
+}
+
+impl<F: Float> Module<F> for Layer<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let rows = to_rows(inputs)?;
+        self.build(rows.ncols());
+        if rows.ncols() != self.weights.nrows() {
+            let mut expected = inputs.shape().to_vec();
+            expected[inputs.ndim() - 1] = self.weights.nrows();
+            return Err(NnError::ShapeMismatch { expected, found: inputs.shape().to_vec() });
+        }
+
+        let mut shape = inputs.shape().to_vec();
+        shape[inputs.ndim() - 1] = self.weights.ncols();
+        let mut outputs = rows.dot(&self.weights);
+        if let Some(biases) = &self.biases {
+            outputs += biases;
+        }
+        self.outputs = Some(reshape(outputs, &shape));
         self.inputs = Some(inputs.clone());
-        self.outputs = inputs.dot(&self.weights) + &self.biases;
+        Ok(())
     }
 
-    pub fn backward(&mut self, dvalues: &Array2<f64>) {
-        let x = self.inputs.as_ref().expect("No input set. Call `forward` before `backward`.");
-        self.dweights = Some(x.t().dot(dvalues));
-        self.dbiases = Some(dvalues.sum_axis(Axis(0)));
-        self.dinputs = Some(dvalues.dot(&self.weights.t()));
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let inputs = self.inputs.as_ref().ok_or(NnError::CallOrder { missing: "inputs", call: "forward" })?;
+        check_shape(dvalues, self.outputs()?.shape())?;
+        let (x, dvalues_rows) = (to_rows(inputs)?, to_rows(dvalues)?);
+
+        self.dweights = Some(x.t().dot(&dvalues_rows));
+        self.dbiases = self.biases.as_ref().map(|_| dvalues_rows.sum_axis(Axis(0)));
+        self.dinputs = Some(reshape(dvalues_rows.dot(&self.weights.t()), inputs.shape()));
+        Ok(())
     }
 
-    pub fn inputs(&self) -> &Array2<f64> {
-        self.inputs.as_ref().expect("No input set. Make sure to call `forward` first.")
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
     }
 
-    pub fn dweights(&self) -> &Array2<f64> {
-        self.dweights.as_ref().expect("dweights not yet set. Make sure to call `backward` first.")
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
     }
 
-    pub fn dbiases(&self) -> &Array1<f64> {
-        self.dbiases.as_ref().expect("dbiases not yet set. Make sure to call `backward` first.")
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dweights = self.dweights.as_ref().ok_or(NnError::CallOrder { missing: "dweights", call: "backward" })?;
+        let mut params = vec![Param { 
+            id: self.weights_id, 
+            values: self.weights.view_mut().into_dyn(), 
+            grads: dweights.view().into_dyn(), 
+            trainable: self.weights_trainable 
+        }];
+
+        if let Some(biases) = self.biases.as_mut() {
+            let dbiases = self.dbiases.as_ref().ok_or(NnError::CallOrder { missing: "dbiases", call: "backward" })?;
+            params.push(Param { 
+                id: self.biases_id, 
+                values: biases.view_mut().into_dyn(), 
+                grads: dbiases.view().into_dyn(), 
+                trainable: self.biases_trainable 
+            });
+        }
+        Ok(params)
     }
 
-    pub fn dinputs(&self) -> &Array2<f64> {
-        self.dinputs.as_ref().expect("dbdinputs not yet set. Make sure to call `backward` first.")
+    fn set_trainable(&mut self, trainable: bool) {
+        self.weights_trainable = trainable;
+        self.biases_trainable = trainable;
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        datasets::spiral_data, 
+        graph::Graph, 
+        loss_functions::SoftmaxCategoricalCrossEntropy, 
+        model::{Model, Sequential}, 
+        normalization::BatchNorm, 
+        optimizers::SGD, 
+        testing::{assert_gradients, random}
+    };
 
-    pub fn weight_momentums(&self) -> &Array2<f64> {
-        self.weight_momentums.as_ref().expect("weights_momentum not yet set. Make sure to update layer params first.")
+    #[test]
+    fn layer_gradients() {
+        assert_gradients(&mut Layer::new(4, 3), &random(&[3, 4], 1));
+        assert_gradients(&mut Layer::new(4, 3), &random(&[2, 5, 4], 1));
     }
 
-    pub fn bias_momentums(&self) -> &Array1<f64> {
-        self.bias_momentums.as_ref().expect("bias_momentum not yet set. Make sure to update layer params first.")
+    #[test]
+    fn lazy_layers_size_on_first_forward() {
+        let mut layer = Layer::lazy_with_initializers(4, Initializer::Constant(0.5), Initializer::Constant(0.25));
+        assert!(!layer.is_built());
+        assert_eq!(layer.weights.dim(), (0, 4));
+
+        layer.forward(&random(&[2, 3], 1)).unwrap();
+        assert!(layer.is_built());
+        assert_eq!(layer.weights, Array2::from_elem((3, 4), 0.5));
+        assert_eq!(layer.biases, Some(Array1::from_elem(4, 0.25)));
+
+        // The width is fixed by the first input.
+        assert!(matches!(layer.forward(&random(&[2, 5], 1)), Err(NnError::ShapeMismatch { .. })));
+        assert!(Layer::<f64>::new(3, 4).is_built());
     }
 
-    pub fn weight_cache(&self) -> &Array2<f64> {
-        self.weight_cache.as_ref().expect("weight_cache not yet set. Make sure to update layer params with a weight-caching optimizer first.")
+    fn sequential() -> Sequential {
+        let mut model = Sequential::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(SGD::new(1.0, 0.0, 0.0)));
+        model.add(Layer::lazy(3));
+        model.add(BatchNorm::new(3));
+        model
     }
 
-    pub fn bias_cache(&self) -> &Array1<f64> {
-        self.bias_cache.as_ref().expect("bias_cache not yet set. Make sure to update layer params with a weight-caching optimizer first.")
+    #[test]
+    fn build_sizes_models_before_fit() {
+        let mut model = sequential();
+        model.build(&[2]).unwrap();
+        assert!(matches!(model.layers[0].forward(&random(&[1, 5], 1)), Err(NnError::ShapeMismatch { .. })));
+        let (x, y) = spiral_data(10, 3);
+        model.fit(&x, &y, 2, 8, None).unwrap();
+
+        let mut graph = Graph::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(SGD::new(1.0, 0.0, 0.0)));
+        let hidden = graph.add(Layer::lazy(4), graph.input()).unwrap();
+        let output = graph.add(Layer::lazy(3), hidden).unwrap();
+        graph.build(&[2]).unwrap();
+        assert!(matches!(graph.module_mut(hidden).unwrap().forward(&random(&[1, 5], 1)), Err(NnError::ShapeMismatch { .. })));
+        assert!(matches!(graph.module_mut(output).unwrap().forward(&random(&[1, 5], 1)), Err(NnError::ShapeMismatch { .. })));
+    }
+
+    #[test]
+    fn build_leaves_batchnorm_statistics_and_training_mode() {
+        let batchnorm_model = || {
+            let mut model = Sequential::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(SGD::new(1.0, 0.0, 0.0)));
+            model.add(BatchNorm::new(2));
+            model
+        };
+        let x = random(&[6, 2], 1);
+        let y = Array1::from_vec(vec![0, 1, 0, 1, 0, 1]);
+
+        // Running statistics untouched: inference matches a model that was never built.
+        let mut built = batchnorm_model();
+        built.build(&[2]).unwrap();
+        assert_eq!(built.predict(&x).unwrap(), batchnorm_model().predict(&x).unwrap());
+
+        // Training mode restored: a forward pass normalizes with batch statistics, giving zero-mean features.
+        let mut built = batchnorm_model();
+        built.build(&[2]).unwrap();
+        built.forward(&x, &y).unwrap();
+        let normalized = built.layers[0].outputs().unwrap();
+        assert!(normalized.mean_axis(Axis(0)).unwrap().iter().all(|mean| mean.abs() < 1e-12));
+    }
+
+    #[test]
+    fn layers_without_bias_have_one_param_and_train() {
+        let mut layer = Layer::new(2, 3).without_bias();
+        assert_gradients(&mut layer, &random(&[4, 2], 1));
+        assert_eq!(layer.params().unwrap().len(), 1);
+        assert!(layer.dbiases().is_err());
+
+        let weights = layer.weights.clone();
+        let mut model = Sequential::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(SGD::new(1.0, 0.0, 0.0)));
+        model.add(layer);
+        let (x, y) = spiral_data(10, 3);
+        model.fit(&x, &y, 2, 8, None).unwrap();
+
+        let params = model.layers[0].params().unwrap();
+        assert_eq!(params.len(), 1);
+        assert_ne!(params[0].values, weights.view().into_dyn());
     }
 }
\ No newline at end of file
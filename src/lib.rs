@@ -0,0 +1,70 @@
+pub mod activations;
+pub mod attention;
+pub mod convolution;
+pub mod datasets;
+pub mod dropout;
+pub mod embedding;
+pub mod error;
+pub mod float;
+pub mod graph;
+pub mod initializers;
+pub mod layer;
+pub mod loss_functions;
+pub mod model;
+pub mod module;
+pub mod normalization;
+pub mod optimizers;
+pub mod pooling;
+pub mod random;
+pub mod recurrent;
+#[cfg(test)]
+mod testing;
+pub mod transformer;
+pub mod utils;
+
+pub use activations::{ReLU, Softmax};
+pub use attention::MultiHeadAttention;
+pub use convolution::{Conv1D, Conv2D};
+pub use dropout::Dropout;
+pub use embedding::Embedding;
+pub use error::{NnError, Result};
+pub use float::Float;
+pub use graph::{Graph, NodeId};
+pub use initializers::Initializer;
+pub use layer::Layer;
+pub use loss_functions::{CategoricalCrossEntropy, Loss, SoftmaxCategoricalCrossEntropy};
+pub use model::{History, Model, Sequential};
+pub use module::{Module, Param, ParamId};
+pub use normalization::{BatchNorm, LayerNorm, RMSNorm};
+pub use optimizers::{optimizer_from_name, AdaGrad, Adam, Optimizer, RMSProp, SGD};
+pub use pooling::{AvgPool2D, Flatten, GlobalAvgPool2D, GlobalMaxPool2D, MaxPool2D};
+pub use random::set_seed;
+pub use recurrent::{Bidirectional, Merge, Recurrent, GRU, LSTM, RNN};
+pub use transformer::{LearnedPositionalEncoding, SinusoidalPositionalEncoding, TransformerEncoder};
+
+/// Everything needed to build and train a model, for glob import.
+pub mod prelude {
+    pub use crate::{
+        activations::{ReLU, Softmax},
+        attention::MultiHeadAttention,
+        convolution::{Conv1D, Conv2D},
+        datasets::{spiral_data, vertical_data},
+        dropout::Dropout,
+        embedding::Embedding,
+        error::{NnError, Result},
+        float::Float,
+        graph::{Graph, NodeId},
+        initializers::Initializer,
+        layer::Layer,
+        loss_functions::{CategoricalCrossEntropy, Loss, SoftmaxCategoricalCrossEntropy},
+        model::{History, Model, Sequential},
+        module::Module,
+        normalization::{BatchNorm, LayerNorm, RMSNorm},
+        optimizers::{optimizer_from_name, AdaGrad, Adam, Optimizer, RMSProp, SGD},
+        pooling::{AvgPool2D, Flatten, GlobalAvgPool2D, GlobalMaxPool2D, MaxPool2D},
+        random::set_seed,
+        recurrent::{Bidirectional, Merge, Recurrent, GRU, LSTM, RNN},
+        transformer::{LearnedPositionalEncoding, SinusoidalPositionalEncoding, TransformerEncoder},
+        utils::accuracy
+    };
+}
\ No newline at end of file
@@ -1,115 +1,226 @@
-#![allow(dead_code)]
+use ndarray::{Array1, Array2, ArrayD, Axis, Ix2, Zip};
 
-use ndarray::{Array, Array1, Array2, Axis, Zip};
-use ndarray_linalg::InnerProduct;
+use crate::{
+    activations::Softmax, 
+    error::{NnError, Result}, 
+    float::Float, 
+    module::Module, 
+    utils::{clip, to_one_hot, to_rank, to_sparse}
+};
 
-use crate::{activations::Softmax, utils::{clip, to_one_hot, to_sparse}};
+/// Shared interface of the losses, computed against sparse class labels. Inputs must be (batch, classes).
+pub trait Loss<F: Float = f64> {
+    fn forward(&mut self, inputs: &ArrayD<F>, y_true: &Array1<usize>) -> Result<F>;
 
-pub struct CategoricalCrossEntropy {
-    pub dinputs: Option<Array2<f64>>
+    fn backward(&mut self, y_true: &Array1<usize>) -> Result<()>;
+
+    fn outputs(&self) -> Result<&Array2<F>>;
+
+    fn dinputs(&self) -> Result<&ArrayD<F>>;
+
+    /// Maps the model's raw outputs to predictions without needing labels.
+    fn predictions(&mut self, inputs: &ArrayD<F>) -> Result<Array2<F>>;
+}
+
+fn check_sparse_labels<F: Float>(y_pred: &Array2<F>, y_true: &Array1<usize>) -> Result<()> {
+    if y_pred.nrows() == 0 {
+        return Err(NnError::EmptyBatch);
+    }
+    if y_pred.nrows() != y_true.len() {
+        return Err(NnError::ShapeMismatch { expected: vec![y_pred.nrows()], found: vec![y_true.len()] });
+    }
+    match y_true.iter().find(|&&class| class >= y_pred.ncols()) {
+        Some(class) => Err(NnError::InvalidLabel(format!("class {} out of range for {} classes", class, y_pred.ncols()))),
+        None => Ok(())
+    }
+}
+
+fn check_one_hot_labels<F: Float>(y_pred: &Array2<F>, y_true: &Array2<usize>) -> Result<()> {
+    if y_pred.nrows() == 0 {
+        return Err(NnError::EmptyBatch);
+    }
+    if y_pred.dim() != y_true.dim() {
+        return Err(NnError::ShapeMismatch { expected: y_pred.shape().to_vec(), found: y_true.shape().to_vec() });
+    }
+    match y_true.axis_iter(Axis(0)).position(|row| !row.iter().any(|&x| x == 1)) {
+        Some(sample) => Err(NnError::InvalidLabel(format!("no target class in label of sample {}", sample))),
+        None => Ok(())
+    }
+}
+
+pub struct CategoricalCrossEntropy<F: Float = f64> {
+    pub inputs: Option<Array2<F>>,
+    pub dinputs: Option<ArrayD<F>>
 }
 
-impl CategoricalCrossEntropy {
+impl<F: Float> CategoricalCrossEntropy<F> {
     pub fn new() -> Self {
-        CategoricalCrossEntropy{ dinputs: None }
+        CategoricalCrossEntropy{ inputs: None, dinputs: None }
     }
 
-    pub fn forward_sparse(&self, y_pred: &Array2<f64>, y_true: &Array1<usize>) -> f64 {
-        let y_pred_clipped = clip(&y_pred, f64::MIN, 1.0 - f64::MIN);
+    pub fn forward_sparse(&self, y_pred: &Array2<F>, y_true: &Array1<usize>) -> Result<F> {
+        check_sparse_labels(y_pred, y_true)?;
+        let y_pred_clipped = clip(y_pred, F::min_value(), F::one() - F::min_value());
 
-        let confs: Array1<f64> = y_pred_clipped
+        let confs: Array1<F> = y_pred_clipped
             .axis_iter(Axis(0))
             .zip(y_true)
             .map(|(sm, ct)| sm[*ct])
             .collect();
 
         let losses = confs.mapv(|x| -x.ln());
-        losses.mean().expect("Losses array was unexpectedly empty")
+        losses.mean().ok_or(NnError::EmptyBatch)
     }
 
-    pub fn forward_one_hot(&self, y_pred: &Array2<f64>, y_true: &Array2<usize>) -> f64 {
-        let y_pred_clipped = clip(&y_pred, f64::MIN, 1.0 - f64::MIN);
+    pub fn forward_one_hot(&self, y_pred: &Array2<F>, y_true: &Array2<usize>) -> Result<F> {
+        check_one_hot_labels(y_pred, y_true)?;
+        let y_pred_clipped = clip(y_pred, F::min_value(), F::one() - F::min_value());
 
-        let confs: Array1<f64> = y_pred_clipped
+        let confs: Array1<F> = y_pred_clipped
             .axis_iter(Axis(0))
-            .zip(y_true.axis_iter(Axis(0)))
-            .map(|(sm, ct)| {
-                let class_idx = ct
-                    .iter()
-                    .position(|&x| x == 1)
-                    .expect("No target class in label");
-                sm[class_idx]
-            })
+            .zip(to_sparse(y_true))
+            .map(|(sm, class_idx)| sm[class_idx])
             .collect();
 
         let losses = confs.mapv(|x| -x.ln());
-        losses.mean().expect("Losses array was unexpectedly empty")
+        losses.mean().ok_or(NnError::EmptyBatch)
     }
 
-    pub fn backward_one_hot(&mut self, dvalues: Array2<f64>, y_true: &Array2<usize>) {
-        let samples = dvalues.dim().0 as f64;
-        let y_true_f64 = y_true.mapv(|x| x as f64);
-        self.dinputs = Some((-y_true_f64 / dvalues) / samples);
+    pub fn backward_one_hot(&mut self, dvalues: Array2<F>, y_true: &Array2<usize>) -> Result<()> {
+        check_one_hot_labels(&dvalues, y_true)?;
+        let samples = F::cast(dvalues.dim().0 as f64);
+        let y_true_float = y_true.mapv(|x| F::cast(x as f64));
+        self.dinputs = Some(((-y_true_float / dvalues) / samples).into_dyn());
+        Ok(())
     }
 
-    pub fn backward_sparse(&mut self, dvalues: Array2<f64>, y_true: Array1<usize>) {
+    pub fn backward_sparse(&mut self, dvalues: Array2<F>, y_true: Array1<usize>) -> Result<()> {
+        check_sparse_labels(&dvalues, &y_true)?;
         let one_hot = to_one_hot(y_true, dvalues.dim().1);
-        self.backward_one_hot(dvalues, &one_hot);
+        self.backward_one_hot(dvalues, &one_hot)
     }
 }
 
-pub struct SoftmaxCategoricalCrossEntropy {
-    pub fn_activation: Softmax,
-    pub fn_loss: CategoricalCrossEntropy,
-    pub output: Option<Array2<f64>>,
-    pub dinputs: Option<Array2<f64>>
+impl<F: Float> Default for CategoricalCrossEntropy<F> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl SoftmaxCategoricalCrossEntropy {
+impl<F: Float> Loss<F> for CategoricalCrossEntropy<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>, y_true: &Array1<usize>) -> Result<F> {
+        let inputs = to_rank::<F, Ix2>(inputs)?.to_owned();
+        let loss = self.forward_sparse(&inputs, y_true);
+        self.inputs = Some(inputs);
+        loss
+    }
+
+    fn backward(&mut self, y_true: &Array1<usize>) -> Result<()> {
+        let inputs = self.outputs()?.clone();
+        self.backward_sparse(inputs, y_true.clone())
+    }
+
+    fn outputs(&self) -> Result<&Array2<F>> {
+        self.inputs.as_ref().ok_or(NnError::CallOrder { missing: "inputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn predictions(&mut self, inputs: &ArrayD<F>) -> Result<Array2<F>> {
+        Ok(to_rank::<F, Ix2>(inputs)?.to_owned())
+    }
+}
+
+pub struct SoftmaxCategoricalCrossEntropy<F: Float = f64> {
+    pub fn_activation: Softmax<F>,
+    pub fn_loss: CategoricalCrossEntropy<F>,
+    pub output: Option<Array2<F>>,
+    pub dinputs: Option<ArrayD<F>>
+}
+
+impl<F: Float> SoftmaxCategoricalCrossEntropy<F> {
     pub fn new() -> Self {
         let fn_activation = Softmax::new();
         let fn_loss = CategoricalCrossEntropy::new();
 
         SoftmaxCategoricalCrossEntropy{
-            fn_activation: fn_activation,
-            fn_loss: fn_loss,
+            fn_activation,
+            fn_loss,
             output: None,
             dinputs: None
         }
     }
 
-    pub fn forward_one_hot(&mut self, inputs: Array2<f64>, y_true: &Array2<usize>) -> f64 {
-        self.fn_activation.forward(&inputs);
-        self.output = Some(self.fn_activation.outputs().clone());
-        self.fn_loss.forward_one_hot(&self.output.as_ref().expect("Outputs array was unexpectedly empty."), &y_true)
+    pub fn forward_one_hot(&mut self, inputs: ArrayD<F>, y_true: &Array2<usize>) -> Result<F> {
+        to_rank::<F, Ix2>(&inputs)?;
+        self.fn_activation.forward(&inputs)?;
+        self.output = Some(to_rank::<F, Ix2>(self.fn_activation.outputs()?)?.to_owned());
+        self.fn_loss.forward_one_hot(self.outputs()?, y_true)
     }
 
-    pub fn forward_sparse(&mut self, inputs: &Array2<f64>, y_true: &Array1<usize>) -> f64 {
-        self.fn_activation.forward(&inputs);
-        self.output = Some(self.fn_activation.outputs().clone());
-        self.fn_loss.forward_sparse(&self.output.as_ref().expect("Outputs array was unexpectedly empty."), &y_true)
+    pub fn forward_sparse(&mut self, inputs: &ArrayD<F>, y_true: &Array1<usize>) -> Result<F> {
+        to_rank::<F, Ix2>(inputs)?;
+        self.fn_activation.forward(inputs)?;
+        self.output = Some(to_rank::<F, Ix2>(self.fn_activation.outputs()?)?.to_owned());
+        self.fn_loss.forward_sparse(self.outputs()?, y_true)
     }
 
-    pub fn backward_sparse(&mut self, dvalues: &Array2<f64>, y_true: &Array1<usize>) {
-        let samples = dvalues.dim().0 as f64;
+    pub fn backward_sparse(&mut self, dvalues: &Array2<F>, y_true: &Array1<usize>) -> Result<()> {
+        check_sparse_labels(dvalues, y_true)?;
+        let samples = F::cast(dvalues.dim().0 as f64);
         let mut dinputs = dvalues.clone();
         Zip::from(dinputs.rows_mut())
             .and(y_true)
-            .for_each(|mut row, &col_idx| row[col_idx] -= 1.0);
+            .for_each(|mut row, &col_idx| row[col_idx] -= F::one());
 
-        self.dinputs = Some(dinputs / samples);
+        self.dinputs = Some((dinputs / samples).into_dyn());
+        Ok(())
     }
 
-    pub fn backward_one_hot(&mut self, dvalues: &Array2<f64>, y_true: &Array2<usize>) {
+    pub fn backward_one_hot(&mut self, dvalues: &Array2<F>, y_true: &Array2<usize>) -> Result<()> {
+        check_one_hot_labels(dvalues, y_true)?;
         let y_true_sparse = to_sparse(y_true);
-        self.backward_sparse(dvalues, &y_true_sparse);
+        self.backward_sparse(dvalues, &y_true_sparse)
+    }
+
+    pub fn outputs(&self) -> Result<&Array2<F>> {
+        self.output.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    pub fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+}
+
+impl<F: Float> Default for SoftmaxCategoricalCrossEntropy<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Float> Loss<F> for SoftmaxCategoricalCrossEntropy<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>, y_true: &Array1<usize>) -> Result<F> {
+        self.forward_sparse(inputs, y_true)
+    }
+
+    fn backward(&mut self, y_true: &Array1<usize>) -> Result<()> {
+        let outputs = self.outputs()?.clone();
+        self.backward_sparse(&outputs, y_true)
+    }
+
+    fn outputs(&self) -> Result<&Array2<F>> {
+        SoftmaxCategoricalCrossEntropy::outputs(self)
     }
 
-    pub fn outputs(&self) -> &Array2<f64> {
-        self.output.as_ref().expect("Outputs unexpectedy empty. Be sure to call `forward` first.")
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        SoftmaxCategoricalCrossEntropy::dinputs(self)
     }
 
-    pub fn dinputs(&self) -> &Array2<f64> {
-        self.dinputs.as_ref().expect("Dinputs unexpectedy empty. Be sure to call `backward` first.")
+    fn predictions(&mut self, inputs: &ArrayD<F>) -> Result<Array2<F>> {
+        to_rank::<F, Ix2>(inputs)?;
+        self.fn_activation.forward(inputs)?;
+        Ok(to_rank::<F, Ix2>(self.fn_activation.outputs()?)?.to_owned())
     }
 }
\ No newline at end of file
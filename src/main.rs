@@ -1,67 +1,35 @@
-mod layer;
-mod utils;
-mod activations;
-mod loss_functions;
-mod datasets;
-mod optimizers;
-
-use std::{backtrace, cmp::max, collections::HashMap};
 use maplit::hashmap;
 
-use ndarray_linalg::krylov::R;
-use rand_distr::StandardNormal;
-use ndarray::{Array2, Array1, array, Axis, s};
-use ndarray_rand::RandomExt;
-
-use crate::{
-    activations::{ReLU, Softmax}, 
-    datasets::{spiral_data, vertical_data}, 
-    layer::Layer, 
-    loss_functions::{CategoricalCrossEntropy, SoftmaxCategoricalCrossEntropy}, 
-    optimizers::{AdaGrad, RMSProp, SGD, Adam}, utils::{accuracy, diagflat}
-};
-
-fn main() {
-    let mut opt = Adam::new();
-    opt.set_hyperparams(hashmap! {
-        "learning_rate" => 0.001
-    });
-    println!("Success!!");
-    
-    // let (x, y) = spiral_data(100, 3);
-    // let batch_size = x.dim().0;
-    
-    // let mut dense1 = Layer::new(2, 64, batch_size);
-    // let mut activation1 = ReLU::new();
-    // let mut dense2 = Layer::new(64, 3, batch_size);
-    // let mut loss_activation = SoftmaxCategoricalCrossEntropy::new();
-    // let mut optimizer = Adam::new(0.02, 1e-5, 1e-7, 0.9, 0.999);
-
-    // let mut loss;
-    // let mut acc;
-
-    // for _epoch in 0..10001 {
-    //     dense1.forward(&x);
-    //     activation1.forward(&dense1.outputs);
-    //     dense2.forward(activation1.outputs());
-    //     loss = loss_activation.forward_sparse(&dense2.outputs, &y);
-    //     acc = accuracy(loss_activation.outputs(), &y);
-
-    //     if _epoch % 100 == 0 {
-    //         println!("epoch: {}", _epoch);
-    //         println!("acc: {}", acc);
-    //         println!("loss: {}", loss);
-    //         println!("lr: {}\n", optimizer.current_learning_rate);
-    //     }
-
-    //     loss_activation.backward_sparse(&loss_activation.outputs().clone(), &y);
-    //     dense2.backward(loss_activation.dinputs());
-    //     activation1.backward(dense2.dinputs());
-    //     dense1.backward(activation1.dinputs());
-
-    //     optimizer.pre_update_params();
-    //     optimizer.update_params(&mut dense1);
-    //     optimizer.update_params(&mut dense2);
-    //     optimizer.post_update_params();
-    // }
-}
+use nns::prelude::*;
+
+fn main() -> Result<()> {
+    let (x, y) = spiral_data(100, 3);
+    let (x_val, y_val) = spiral_data(100, 3);
+    let batch_size = x.dim().0;
+
+    let mut optimizer = Adam::new();
+    optimizer.set_hyperparams(hashmap! {
+        "learning_rate" => 0.02,
+        "decay" => 1e-5
+    })?;
+
+    let mut model = Sequential::new(
+        Box::new(SoftmaxCategoricalCrossEntropy::new()), 
+        Box::new(optimizer)
+    );
+    model.add(Layer::new(2, 64));
+    model.add(ReLU::new());
+    model.add(Layer::new(64, 3));
+
+    let history = model.fit(&x, &y, 10001, batch_size, Some((&x_val, &y_val)))?;
+
+    for epoch in (0..history.loss.len()).step_by(100) {
+        println!("epoch: {}", epoch);
+        println!("acc: {}", history.accuracy[epoch]);
+        println!("loss: {}", history.loss[epoch]);
+        println!("val_acc: {}", history.val_accuracy[epoch]);
+        println!("val_loss: {}\n", history.val_loss[epoch]);
+    }
+
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,312 @@
+use ndarray::{Array, Array1, Array2, ArrayD, Axis, Dimension, Ix2, RemoveAxis};
+use rand::seq::SliceRandom;
+
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    loss_functions::Loss, 
+    module::Module, 
+    optimizers::Optimizer, 
+    random::with_rng, 
+    utils::{accuracy, to_rank}
+};
+
+// Number of samples along the batch axis; scalars have none.
+fn batch_len<F: Float, D: Dimension>(x: &Array<F, D>) -> Result<usize> {
+    if x.ndim() == 0 {
+        return Err(NnError::RankMismatch { expected: 1, found: 0 });
+    }
+    Ok(x.len_of(Axis(0)))
+}
+
+// Marks the steps of (batch, time) token `inputs` equal to `padding_token`; no mask without a padding token.
+pub(crate) fn padding_mask<F: Float>(inputs: &ArrayD<F>, padding_token: Option<usize>) -> Result<Option<Array2<bool>>> {
+    match padding_token {
+        Some(token) => Ok(Some(to_rank::<F, Ix2>(inputs)?.mapv(|value| value.as_f64() == token as f64))),
+        None => Ok(None)
+    }
+}
+
+/// Per-epoch metrics recorded by `Model::fit`.
+pub struct History {
+    pub loss: Vec<f64>,
+    pub accuracy: Vec<f64>,
+    pub val_loss: Vec<f64>,
+    pub val_accuracy: Vec<f64>
+}
+
+/// Training and inference loop shared by `Sequential` and `Graph`: implementors provide a single
+/// forward/backward pass and the parameter update, and get `fit`, `evaluate` and `predict` for free.
+pub trait Model<F: Float = f64> {
+    /// Runs `inputs` through every module and returns the mean loss against `y_true`.
+    fn forward(&mut self, inputs: &ArrayD<F>, y_true: &Array1<usize>) -> Result<F>;
+
+    /// Propagates the loss gradient back through every module.
+    fn backward(&mut self, y_true: &Array1<usize>) -> Result<()>;
+
+    /// Applies one optimizer step to the parameters of every module.
+    fn update_params(&mut self) -> Result<()>;
+
+    /// Puts every module in training or inference mode.
+    fn set_training(&mut self, training: bool);
+
+    /// Predictions of the last `forward` call, as produced by the loss.
+    fn outputs(&self) -> Result<&Array2<F>>;
+
+    /// Runs `inputs` through every module and maps the result to predictions without needing labels.
+    fn predictions(&mut self, inputs: &ArrayD<F>) -> Result<Array2<F>>;
+
+    /// Trains for `epochs` passes over shuffled mini-batches of `batch_size` samples,
+    /// evaluating on `validation_data` after every epoch when given. Samples lie along the first axis of `x`.
+    fn fit<D: RemoveAxis>(
+        &mut self,
+        x: &Array<F, D>,
+        y: &Array1<usize>,
+        epochs: usize,
+        batch_size: usize,
+        validation_data: Option<(&Array<F, D>, &Array1<usize>)>
+    ) -> Result<History> {
+        if batch_size == 0 {
+            return Err(NnError::InvalidHyperparameter("batch_size must be greater than zero".to_string()));
+        }
+        let n_samples = batch_len(x)?;
+        if n_samples == 0 {
+            return Err(NnError::EmptyBatch);
+        }
+        if n_samples != y.len() {
+            return Err(NnError::ShapeMismatch { expected: vec![n_samples], found: vec![y.len()] });
+        }
+
+        let mut indices: Vec<usize> = (0..n_samples).collect();
+        let mut history = History {
+            loss: Vec::with_capacity(epochs),
+            accuracy: Vec::with_capacity(epochs),
+            val_loss: Vec::new(),
+            val_accuracy: Vec::new()
+        };
+
+        for _epoch in 0..epochs {
+            self.set_training(true);
+            with_rng(|rng| indices.shuffle(rng));
+
+            let mut epoch_loss = 0.0;
+            let mut epoch_acc = 0.0;
+
+            for batch in indices.chunks(batch_size) {
+                let x_batch = x.select(Axis(0), batch).into_dyn();
+                let y_batch = y.select(Axis(0), batch);
+
+                let loss = self.forward(&x_batch, &y_batch)?.as_f64();
+                let acc = accuracy(self.outputs()?, &y_batch)?;
+                epoch_loss += loss * batch.len() as f64;
+                epoch_acc += acc * batch.len() as f64;
+
+                self.backward(&y_batch)?;
+                self.update_params()?;
+            }
+
+            history.loss.push(epoch_loss / n_samples as f64);
+            history.accuracy.push(epoch_acc / n_samples as f64);
+
+            if let Some((x_val, y_val)) = validation_data {
+                let (val_loss, val_acc) = self.evaluate(x_val, y_val)?;
+                history.val_loss.push(val_loss);
+                history.val_accuracy.push(val_acc);
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// Loss and accuracy of the model on `x` in inference mode, without updating any weights.
+    fn evaluate<D: Dimension>(&mut self, x: &Array<F, D>, y: &Array1<usize>) -> Result<(f64, f64)> {
+        batch_len(x)?;
+        self.set_training(false);
+        let loss = self.forward(&x.clone().into_dyn(), y)?.as_f64();
+        Ok((loss, accuracy(self.outputs()?, y)?))
+    }
+
+    /// Predictions of the model on `x` in inference mode, without updating any weights.
+    fn predict<D: Dimension>(&mut self, x: &Array<F, D>) -> Result<Array2<F>> {
+        batch_len(x)?;
+        self.set_training(false);
+        self.predictions(&x.clone().into_dyn())
+    }
+
+    /// Sizes lazily built modules such as `Layer::lazy` by running a zero batch of one sample of shape
+    /// `input_shape` through the model in inference mode. Leaves the model in training mode.
+    fn build(&mut self, input_shape: &[usize]) -> Result<()> {
+        let mut shape = vec![1];
+        shape.extend_from_slice(input_shape);
+
+        self.set_training(false);
+        let result = self.predictions(&ArrayD::zeros(shape));
+        self.set_training(true);
+        result.map(|_| ())
+    }
+}
+
+/// An ordered stack of modules trained end to end against a single loss.
+pub struct Sequential<F: Float = f64> {
+    pub layers: Vec<Box<dyn Module<F>>>,
+    pub loss: Box<dyn Loss<F>>,
+    pub optimizer: Box<dyn Optimizer<F>>,
+    /// Token ID marking padded steps of (batch, time) inputs. Every batch is scanned for it and the resulting
+    /// mask is handed to each module, so attention layers skip the padding of exactly the samples in the batch.
+    pub padding_token: Option<usize>
+}
+
+impl<F: Float> Sequential<F> {
+    pub fn new(loss: Box<dyn Loss<F>>, optimizer: Box<dyn Optimizer<F>>) -> Self {
+        Sequential {
+            layers: Vec::new(),
+            loss,
+            optimizer,
+            padding_token: None
+        }
+    }
+
+    pub fn add<M: Module<F> + 'static>(&mut self, module: M) {
+        self.layers.push(Box::new(module));
+    }
+
+    fn forward_layers(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        if self.layers.is_empty() {
+            return Err(NnError::CallOrder { missing: "layers", call: "add" });
+        }
+
+        let mask = padding_mask(inputs, self.padding_token)?;
+        for module in self.layers.iter_mut() {
+            module.set_padding_mask(mask.as_ref());
+        }
+
+        self.layers[0].forward(inputs)?;
+        for i in 1..self.layers.len() {
+            let (prev, rest) = self.layers.split_at_mut(i);
+            rest[0].forward(prev[i - 1].outputs()?)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: Float> Model<F> for Sequential<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>, y_true: &Array1<usize>) -> Result<F> {
+        self.forward_layers(inputs)?;
+        let last = self.layers.len() - 1;
+        self.loss.forward(self.layers[last].outputs()?, y_true)
+    }
+
+    /// Propagates the loss gradient back through every module, last to first.
+    fn backward(&mut self, y_true: &Array1<usize>) -> Result<()> {
+        if self.layers.is_empty() {
+            return Err(NnError::CallOrder { missing: "layers", call: "add" });
+        }
+
+        self.loss.backward(y_true)?;
+
+        let last = self.layers.len() - 1;
+        self.layers[last].backward(self.loss.dinputs()?)?;
+        for i in (0..last).rev() {
+            let (head, tail) = self.layers.split_at_mut(i + 1);
+            head[i].backward(tail[0].dinputs()?)?;
+        }
+        Ok(())
+    }
+
+    fn update_params(&mut self) -> Result<()> {
+        self.optimizer.pre_update_params();
+        for module in self.layers.iter_mut() {
+            self.optimizer.update_params(module.as_mut())?;
+        }
+        self.optimizer.post_update_params();
+        Ok(())
+    }
+
+    fn set_training(&mut self, training: bool) {
+        for module in self.layers.iter_mut() {
+            module.set_training(training);
+        }
+    }
+
+    fn outputs(&self) -> Result<&Array2<F>> {
+        self.loss.outputs()
+    }
+
+    fn predictions(&mut self, inputs: &ArrayD<F>) -> Result<Array2<F>> {
+        self.forward_layers(inputs)?;
+        let last = self.layers.len() - 1;
+        self.loss.predictions(self.layers[last].outputs()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::s;
+
+    use super::*;
+    use crate::{
+        activations::ReLU, 
+        attention::MultiHeadAttention, 
+        datasets::spiral_data, 
+        embedding::Embedding, 
+        layer::Layer, 
+        loss_functions::SoftmaxCategoricalCrossEntropy, 
+        optimizers::SGD, 
+        pooling::Flatten
+    };
+
+    fn model() -> Sequential {
+        let mut model = Sequential::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(SGD::new(1.0, 0.0, 0.0)));
+        model.add(Layer::new(2, 8));
+        model.add(ReLU::new());
+        model.add(Layer::new(8, 3));
+        model
+    }
+
+    #[test]
+    fn fit_records_every_epoch() {
+        let (x, y) = spiral_data(20, 3);
+        let history = model().fit(&x, &y, 5, 16, Some((&x, &y))).unwrap();
+        assert_eq!(history.loss.len(), 5);
+        assert_eq!(history.val_accuracy.len(), 5);
+    }
+
+    #[test]
+    fn diverged_training_returns_an_error() {
+        let (x, y) = spiral_data(20, 3);
+        let mut model = model();
+        model.add(Layer::new(3, 3));
+        let mut diverged = Layer::new(3, 3);
+        diverged.weights.fill(f64::NAN);
+        model.add(diverged);
+
+        assert!(matches!(model.fit(&x, &y, 1, 16, None), Err(NnError::NonFinite(_))));
+        assert!(matches!(model.evaluate(&x, &y), Err(NnError::NonFinite(_))));
+    }
+
+    #[test]
+    fn padding_masks_follow_shuffled_batches() {
+        // Sample i holds 1 + i % 5 tokens followed by padding with token 0.
+        let x = Array2::from_shape_fn((10, 5), |(i, t)| if t <= i % 5 { ((i + t) % 5 + 1) as f64 } else { 0.0 });
+        let y = Array1::from_shape_fn(10, |i| i % 2);
+
+        let mut model = Sequential::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(SGD::new(0.1, 0.0, 0.0)));
+        model.padding_token = Some(0);
+        model.add(Embedding::new(6, 4));
+        model.add(MultiHeadAttention::new(4, 2, false).unwrap());
+        model.add(Flatten::new());
+        model.add(Layer::new(20, 2));
+
+        // Batches of 4 leave a final batch of 2, and every batch is a different shuffled subset.
+        let history = model.fit(&x, &y, 3, 4, Some((&x, &y))).unwrap();
+        assert_eq!(history.loss.len(), 3);
+
+        let predictions = model.predict(&x).unwrap();
+        for i in 0..10 {
+            let single = model.predict(&x.slice(s![i..i + 1, ..]).to_owned()).unwrap();
+            assert!((&single.row(0) - &predictions.row(i)).iter().all(|diff| diff.abs() < 1e-12), "sample {}", i);
+        }
+
+        assert!(matches!(model.predict(&ArrayD::<f64>::zeros(vec![2, 5, 1])), Err(NnError::RankMismatch { expected: 2, found: 3 })));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,54 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ndarray::{Array2, ArrayD, ArrayViewD, ArrayViewMutD};
+
+use crate::{error::Result, float::Float};
+
+/// Identifies a trainable parameter, so optimizers can key their per-parameter state by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParamId(usize);
+
+impl ParamId {
+    pub fn unique() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        ParamId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// A parameter and its gradient, as handed to an optimizer.
+pub struct Param<'a, F: Float = f64> {
+    pub id: ParamId,
+    pub values: ArrayViewMutD<'a, F>,
+    pub grads: ArrayViewD<'a, F>,
+    /// Frozen parameters still get gradients but are left untouched by every optimizer.
+    pub trainable: bool
+}
+
+/// Shared interface of every layer and activation, so models can hold `Vec<Box<dyn Module<F>>>`.
+/// Inputs are dynamically shaped with the batch on the first axis; each module documents the rank it expects.
+pub trait Module<F: Float = f64> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()>;
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()>;
+
+    fn outputs(&self) -> Result<&ArrayD<F>>;
+
+    fn dinputs(&self) -> Result<&ArrayD<F>>;
+
+    /// Trainable parameters with their gradients from the last `backward`; empty for modules without any.
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        Ok(Vec::new())
+    }
+
+    /// Switches between training and inference behavior; a no-op for modules that behave the same in both.
+    fn set_training(&mut self, _training: bool) {}
+
+    /// Freezes (`false`) or unfreezes every parameter of the module, e.g. to fine-tune only the last layers
+    /// of a pretrained model; a no-op for modules without parameters.
+    fn set_trainable(&mut self, _trainable: bool) {}
+
+    /// Marks the padded steps, of shape (batch, time), of the batch passed to the next `forward`; `None` clears
+    /// the mask. A no-op for modules that do not attend over time. `Sequential` and `Graph` call it before every
+    /// forward pass, so the mask always matches the batch.
+    fn set_padding_mask(&mut self, _mask: Option<&Array2<bool>>) {}
+}
\ No newline at end of file
@@ -0,0 +1,484 @@
+use ndarray::{Array1, Array2, ArrayD, Axis, CowArray, Ix2};
+
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    module::{Module, Param, ParamId}, 
+    utils::{check_shape, reshape, to_rank, to_rows}
+};
+
+/// Batch normalization over axis 1, with a learnable scale (`gamma`) and shift (`beta`). Statistics are
+/// taken over every other axis, so (batch, features) and NCHW inputs normalize per feature and per channel.
+/// Running statistics are tracked while training and used in their place for inference.
+pub struct BatchNorm<F: Float = f64> {
+    pub gamma: Array1<F>,
+    pub beta: Array1<F>,
+    pub running_mean: Array1<F>,
+    pub running_var: Array1<F>,
+    pub momentum: f64,
+    pub epsilon: f64,
+    pub training: bool,
+    pub gamma_trainable: bool,
+    pub beta_trainable: bool,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dgamma: Option<Array1<F>>,
+    pub dbeta: Option<Array1<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    normalized: Option<Array2<F>>,
+    inv_std: Option<Array1<F>>,
+    batch_stats: bool,
+    gamma_id: ParamId,
+    beta_id: ParamId
+}
+
+impl<F: Float> BatchNorm<F> {
+    pub fn new(n_features: usize) -> Self {
+        BatchNorm {
+            gamma: Array1::ones(n_features),
+            beta: Array1::zeros(n_features),
+            running_mean: Array1::zeros(n_features),
+            running_var: Array1::ones(n_features),
+            momentum: 0.9,
+            epsilon: 1e-5,
+            training: true,
+            gamma_trainable: true,
+            beta_trainable: true,
+            outputs: None,
+            dgamma: None,
+            dbeta: None,
+            dinputs: None,
+            normalized: None,
+            inv_std: None,
+            batch_stats: false,
+            gamma_id: ParamId::unique(),
+            beta_id: ParamId::unique()
+        }
+    }
+}
+
+impl<F: Float> Module<F> for BatchNorm<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let rows = features_last(inputs, self.gamma.len())?;
+
+        let (mean, var) = if self.training {
+            let mean = rows.mean_axis(Axis(0)).ok_or(NnError::EmptyBatch)?;
+            let var = rows.var_axis(Axis(0), F::zero());
+
+            let momentum = F::cast(self.momentum);
+            self.running_mean = &self.running_mean * momentum + &mean * (F::one() - momentum);
+            self.running_var = &self.running_var * momentum + &var * (F::one() - momentum);
+            (mean, var)
+        }
+
+        else {
+            (self.running_mean.clone(), self.running_var.clone())
+        };
+
+        let epsilon = F::cast(self.epsilon);
+        let inv_std = var.mapv(|v| F::one() / (v + epsilon).sqrt());
+        let normalized = (&rows - &mean) * &inv_std;
+
+        self.outputs = Some(features_back(&normalized * &self.gamma + &self.beta, inputs.shape()));
+        self.normalized = Some(normalized);
+        self.inv_std = Some(inv_std);
+        self.batch_stats = self.training;
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let normalized = self.normalized.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+        let inv_std = self.inv_std.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+        let outputs = self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+        check_shape(dvalues, outputs.shape())?;
+        let dvalues_rows = features_last(dvalues, self.gamma.len())?;
+
+        self.dgamma = Some((&dvalues_rows * normalized).sum_axis(Axis(0)));
+        self.dbeta = Some(dvalues_rows.sum_axis(Axis(0)));
+
+        let dnormalized = &dvalues_rows * &self.gamma;
+        let dinputs = if self.batch_stats {
+            // The batch mean and variance depend on every sample, so their gradients flow back too.
+            let n = F::cast(dvalues_rows.nrows() as f64);
+            let dnormalized_sum = dnormalized.sum_axis(Axis(0));
+            let dnormalized_dot = (&dnormalized * normalized).sum_axis(Axis(0));
+            ((&dnormalized * n - &dnormalized_sum - normalized * &dnormalized_dot) * inv_std) / n
+        }
+
+        else {
+            dnormalized * inv_std
+        };
+        self.dinputs = Some(features_back(dinputs, dvalues.shape()));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dgamma = self.dgamma.as_ref().ok_or(NnError::CallOrder { missing: "dgamma", call: "backward" })?;
+        let dbeta = self.dbeta.as_ref().ok_or(NnError::CallOrder { missing: "dbeta", call: "backward" })?;
+
+        Ok(vec![
+            Param { 
+                id: self.gamma_id, 
+                values: self.gamma.view_mut().into_dyn(), 
+                grads: dgamma.view().into_dyn(), 
+                trainable: self.gamma_trainable 
+            },
+            Param { 
+                id: self.beta_id, 
+                values: self.beta.view_mut().into_dyn(), 
+                grads: dbeta.view().into_dyn(), 
+                trainable: self.beta_trainable 
+            }
+        ])
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.gamma_trainable = trainable;
+        self.beta_trainable = trainable;
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+/// Layer normalization: normalizes each sample over its features, with a learnable gain (`gamma`) and bias (`beta`).
+pub struct LayerNorm<F: Float = f64> {
+    pub gamma: Array1<F>,
+    pub beta: Array1<F>,
+    pub epsilon: f64,
+    pub gamma_trainable: bool,
+    pub beta_trainable: bool,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dgamma: Option<Array1<F>>,
+    pub dbeta: Option<Array1<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    normalized: Option<Array2<F>>,
+    inv_std: Option<Array2<F>>,
+    gamma_id: ParamId,
+    beta_id: ParamId
+}
+
+impl<F: Float> LayerNorm<F> {
+    pub fn new(n_features: usize) -> Self {
+        LayerNorm {
+            gamma: Array1::ones(n_features),
+            beta: Array1::zeros(n_features),
+            epsilon: 1e-5,
+            gamma_trainable: true,
+            beta_trainable: true,
+            outputs: None,
+            dgamma: None,
+            dbeta: None,
+            dinputs: None,
+            normalized: None,
+            inv_std: None,
+            gamma_id: ParamId::unique(),
+            beta_id: ParamId::unique()
+        }
+    }
+}
+
+impl<F: Float> Module<F> for LayerNorm<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let rows = feature_rows(inputs, self.gamma.len())?;
+
+        let mean = rows.mean_axis(Axis(1)).ok_or(NnError::EmptyBatch)?.insert_axis(Axis(1));
+        let var = rows.var_axis(Axis(1), F::zero()).insert_axis(Axis(1));
+        let epsilon = F::cast(self.epsilon);
+        let inv_std = var.mapv(|v| F::one() / (v + epsilon).sqrt());
+        let normalized = (&rows - &mean) * &inv_std;
+
+        self.outputs = Some(reshape(&normalized * &self.gamma + &self.beta, inputs.shape()));
+        self.normalized = Some(normalized);
+        self.inv_std = Some(inv_std);
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let normalized = self.normalized.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+        let inv_std = self.inv_std.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+        let outputs = self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+        check_shape(dvalues, outputs.shape())?;
+        let dvalues_rows = to_rows(dvalues)?;
+
+        self.dgamma = Some((&dvalues_rows * normalized).sum_axis(Axis(0)));
+        self.dbeta = Some(dvalues_rows.sum_axis(Axis(0)));
+
+        let n = F::cast(dvalues_rows.ncols() as f64);
+        let dnormalized = &dvalues_rows * &self.gamma;
+        let dnormalized_sum = dnormalized.sum_axis(Axis(1)).insert_axis(Axis(1));
+        let dnormalized_dot = (&dnormalized * normalized).sum_axis(Axis(1)).insert_axis(Axis(1));
+        let dinputs = ((&dnormalized * n - &dnormalized_sum - normalized * &dnormalized_dot) * inv_std) / n;
+        self.dinputs = Some(reshape(dinputs, dvalues.shape()));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dgamma = self.dgamma.as_ref().ok_or(NnError::CallOrder { missing: "dgamma", call: "backward" })?;
+        let dbeta = self.dbeta.as_ref().ok_or(NnError::CallOrder { missing: "dbeta", call: "backward" })?;
+
+        Ok(vec![
+            Param { 
+                id: self.gamma_id, 
+                values: self.gamma.view_mut().into_dyn(), 
+                grads: dgamma.view().into_dyn(), 
+                trainable: self.gamma_trainable 
+            },
+            Param { 
+                id: self.beta_id, 
+                values: self.beta.view_mut().into_dyn(), 
+                grads: dbeta.view().into_dyn(), 
+                trainable: self.beta_trainable 
+            }
+        ])
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.gamma_trainable = trainable;
+        self.beta_trainable = trainable;
+    }
+}
+
+/// RMS normalization: rescales each sample by the root mean square of its features, without
+/// centering, followed by a learnable gain (`gamma`) and bias (`beta`).
+pub struct RMSNorm<F: Float = f64> {
+    pub gamma: Array1<F>,
+    pub beta: Array1<F>,
+    pub epsilon: f64,
+    pub gamma_trainable: bool,
+    pub beta_trainable: bool,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dgamma: Option<Array1<F>>,
+    pub dbeta: Option<Array1<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    normalized: Option<Array2<F>>,
+    inv_rms: Option<Array2<F>>,
+    gamma_id: ParamId,
+    beta_id: ParamId
+}
+
+impl<F: Float> RMSNorm<F> {
+    pub fn new(n_features: usize) -> Self {
+        RMSNorm {
+            gamma: Array1::ones(n_features),
+            beta: Array1::zeros(n_features),
+            epsilon: 1e-6,
+            gamma_trainable: true,
+            beta_trainable: true,
+            outputs: None,
+            dgamma: None,
+            dbeta: None,
+            dinputs: None,
+            normalized: None,
+            inv_rms: None,
+            gamma_id: ParamId::unique(),
+            beta_id: ParamId::unique()
+        }
+    }
+}
+
+impl<F: Float> Module<F> for RMSNorm<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let rows = feature_rows(inputs, self.gamma.len())?;
+
+        let mean_square = rows.mapv(|v| v * v).mean_axis(Axis(1)).ok_or(NnError::EmptyBatch)?.insert_axis(Axis(1));
+        let epsilon = F::cast(self.epsilon);
+        let inv_rms = mean_square.mapv(|v| F::one() / (v + epsilon).sqrt());
+        let normalized = &rows * &inv_rms;
+
+        self.outputs = Some(reshape(&normalized * &self.gamma + &self.beta, inputs.shape()));
+        self.normalized = Some(normalized);
+        self.inv_rms = Some(inv_rms);
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let normalized = self.normalized.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+        let inv_rms = self.inv_rms.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+        let outputs = self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+        check_shape(dvalues, outputs.shape())?;
+        let dvalues_rows = to_rows(dvalues)?;
+
+        self.dgamma = Some((&dvalues_rows * normalized).sum_axis(Axis(0)));
+        self.dbeta = Some(dvalues_rows.sum_axis(Axis(0)));
+
+        let n = F::cast(dvalues_rows.ncols() as f64);
+        let dnormalized = &dvalues_rows * &self.gamma;
+        let dnormalized_dot = (&dnormalized * normalized).sum_axis(Axis(1)).insert_axis(Axis(1)) / n;
+        self.dinputs = Some(reshape((dnormalized - normalized * &dnormalized_dot) * inv_rms, dvalues.shape()));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dgamma = self.dgamma.as_ref().ok_or(NnError::CallOrder { missing: "dgamma", call: "backward" })?;
+        let dbeta = self.dbeta.as_ref().ok_or(NnError::CallOrder { missing: "dbeta", call: "backward" })?;
+
+        Ok(vec![
+            Param { 
+                id: self.gamma_id, 
+                values: self.gamma.view_mut().into_dyn(), 
+                grads: dgamma.view().into_dyn(), 
+                trainable: self.gamma_trainable 
+            },
+            Param { 
+                id: self.beta_id, 
+                values: self.beta.view_mut().into_dyn(), 
+                grads: dbeta.view().into_dyn(), 
+                trainable: self.beta_trainable 
+            }
+        ])
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.gamma_trainable = trainable;
+        self.beta_trainable = trainable;
+    }
+}
+
+// Rows of the last axis, which must hold `n_features` values.
+fn feature_rows<F: Float>(inputs: &ArrayD<F>, n_features: usize) -> Result<CowArray<'_, F, Ix2>> {
+    let rows = to_rows(inputs)?;
+    if rows.ncols() != n_features {
+        let mut expected = inputs.shape().to_vec();
+        expected[inputs.ndim() - 1] = n_features;
+        return Err(NnError::ShapeMismatch { expected, found: inputs.shape().to_vec() });
+    }
+    Ok(rows)
+}
+
+// Moves the feature axis (axis 1) last and merges the others into rows. 2-D inputs are viewed, not copied.
+fn features_last<F: Float>(inputs: &ArrayD<F>, n_features: usize) -> Result<CowArray<'_, F, Ix2>> {
+    if inputs.ndim() < 2 {
+        return Err(NnError::RankMismatch { expected: 2, found: inputs.ndim() });
+    }
+    if inputs.shape()[1] != n_features {
+        let mut expected = inputs.shape().to_vec();
+        expected[1] = n_features;
+        return Err(NnError::ShapeMismatch { expected, found: inputs.shape().to_vec() });
+    }
+    if inputs.ndim() == 2 {
+        return Ok(to_rank::<F, Ix2>(inputs)?.into());
+    }
+
+    let mut axes: Vec<usize> = (0..inputs.ndim()).collect();
+    axes.remove(1);
+    axes.push(1);
+    let n_rows = inputs.shape().iter().enumerate().filter(|&(axis, _)| axis != 1).map(|(_, &size)| size).product();
+    let moved = inputs.view().permuted_axes(axes).as_standard_layout().into_owned();
+    Ok(moved.into_shape((n_rows, n_features)).expect("Element count is unchanged").into())
+}
+
+// Inverse of `features_last` for an array of `shape`.
+fn features_back<F: Float>(rows: Array2<F>, shape: &[usize]) -> ArrayD<F> {
+    if shape.len() == 2 {
+        return reshape(rows, shape);
+    }
+
+    let mut moved_shape = shape.to_vec();
+    let n_features = moved_shape.remove(1);
+    moved_shape.push(n_features);
+    let mut axes: Vec<usize> = (0..shape.len() - 1).collect();
+    axes.insert(1, shape.len() - 1);
+    reshape(reshape(rows, &moved_shape).permuted_axes(axes), shape)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{Array1, Axis};
+
+    use super::*;
+    use crate::testing::{assert_gradients, random};
+
+    #[test]
+    fn batchnorm_gradients() {
+        let mut batchnorm = BatchNorm::new(4);
+        batchnorm.gamma = Array1::from(vec![0.5, -1.5, 2.0, 1.0]);
+        let inputs = random(&[6, 4], 1) * 3.0 + 1.0;
+        assert_gradients(&mut batchnorm, &inputs);
+
+        batchnorm.set_training(false);
+        assert_gradients(&mut batchnorm, &inputs);
+    }
+
+    #[test]
+    fn batchnorm_normalizes_images_per_channel() {
+        let mut batchnorm = BatchNorm::new(3);
+        let inputs = random(&[4, 3, 2, 5], 2) * 2.0 - 1.0;
+        assert_gradients(&mut batchnorm, &inputs);
+
+        batchnorm.forward(&inputs).unwrap();
+        for channel in batchnorm.outputs().unwrap().axis_iter(Axis(1)) {
+            assert!(channel.mean().unwrap().abs() < 1e-10);
+            assert!((channel.var(0.0) - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn batchnorm_tracks_running_statistics() {
+        let mut batchnorm = BatchNorm::<f64>::new(2);
+        let inputs = random(&[50, 2], 3) + 4.0;
+        for _ in 0..100 {
+            batchnorm.forward(&inputs).unwrap();
+        }
+        let mean = inputs.mean_axis(Axis(0)).unwrap();
+        assert!(batchnorm.running_mean.iter().zip(mean.iter()).all(|(a, b)| (a - b).abs() < 1e-3));
+        assert!(matches!(batchnorm.forward(&random(&[5, 3], 4)), Err(NnError::ShapeMismatch { .. })));
+    }
+
+    #[test]
+    fn layernorm_gradients() {
+        let mut layernorm = LayerNorm::new(6);
+        layernorm.gamma = random(&[6], 5).into_dimensionality().unwrap();
+        assert_gradients(&mut layernorm, &(random(&[5, 6], 6) * 2.0 + 0.5));
+        assert_gradients(&mut layernorm, &random(&[2, 3, 6], 7));
+    }
+
+    #[test]
+    fn rmsnorm_gradients() {
+        let mut rmsnorm = RMSNorm::new(6);
+        rmsnorm.gamma = random(&[6], 8).into_dimensionality().unwrap();
+        assert_gradients(&mut rmsnorm, &(random(&[5, 6], 9) * 2.0 + 0.5));
+        assert_gradients(&mut rmsnorm, &random(&[2, 3, 6], 10));
+    }
+
+    #[test]
+    fn layernorm_normalizes_each_sample() {
+        let mut layernorm = LayerNorm::<f64>::new(8);
+        layernorm.forward(&(random(&[4, 8], 11) * 5.0 + 3.0)).unwrap();
+        for row in layernorm.outputs().unwrap().axis_iter(Axis(0)) {
+            assert!(row.mean().unwrap().abs() < 1e-10);
+            assert!((row.var(0.0) - 1.0).abs() < 1e-3);
+        }
+        assert!(matches!(layernorm.forward(&random(&[4, 7], 12)), Err(NnError::ShapeMismatch { .. })));
+    }
+}
\ No newline at end of file
@@ -1,29 +1,89 @@
-use std::{collections::HashMap, f64::EPSILON};
+use std::collections::HashMap;
 
-use ndarray::{Array1, Array2};
+use ndarray::{ArrayD, ArrayViewD, ArrayViewMutD, IxDyn};
 
-use crate::layer::Layer;
+use crate::{error::{NnError, Result}, float::Float, module::{Module, Param, ParamId}};
 
-pub struct SGD {
+/// Shared update cycle of every optimizer, so training code can hold a `Box<dyn Optimizer>`.
+pub trait Optimizer<F: Float = f64> {
+    fn pre_update_params(&mut self);
+
+    /// Updates every trainable parameter of `module` from its gradients, skipping frozen ones.
+    fn update_params(&mut self, module: &mut dyn Module<F>) -> Result<()> {
+        for param in module.params()? {
+            self.update_param(param)?;
+        }
+        Ok(())
+    }
+
+    /// Applies one step to `param`; a frozen parameter is left untouched, along with its optimizer state.
+    fn update_param(&mut self, param: Param<'_, F>) -> Result<()>;
+
+    fn post_update_params(&mut self);
+
+    fn current_learning_rate(&self) -> f64;
+
+    fn set_hyperparams(&mut self, hyperparams: HashMap<&str, f64>) -> Result<()>;
+}
+
+/// Builds an optimizer from its name (`"sgd"`, `"adagrad"`, `"rmsprop"` or `"adam"`) with default hyperparameters.
+pub fn optimizer_from_name<F: Float>(name: &str) -> Result<Box<dyn Optimizer<F>>> {
+    match name.to_lowercase().as_str() {
+        "sgd" => Ok(Box::new(SGD::new(1.0, 0.0, 0.0))),
+        "adagrad" => Ok(Box::new(AdaGrad::new(1.0, 0.0, 1e-7))),
+        "rmsprop" => Ok(Box::new(RMSProp::new(0.001, 0.0, 1e-7, 0.9))),
+        "adam" => Ok(Box::new(Adam::new())),
+        _ => Err(NnError::InvalidHyperparameter(format!("unknown optimizer \"{}\"", name)))
+    }
+}
+
+// Rejects the whole map if any key is unknown, so a failed `set_hyperparams` leaves the optimizer unchanged.
+fn check_keys(hyperparams: &HashMap<&str, f64>, known: &[&str]) -> Result<()> {
+    match hyperparams.keys().find(|key| !known.contains(key)) {
+        Some(key) => Err(NnError::InvalidHyperparameter(format!("unknown key \"{}\"", key))),
+        None => Ok(())
+    }
+}
+
+fn check_grads<F: Float>(values: &ArrayViewMutD<F>, grads: &ArrayViewD<F>) -> Result<()> {
+    if values.shape() != grads.shape() {
+        return Err(NnError::ShapeMismatch { expected: values.shape().to_vec(), found: grads.shape().to_vec() });
+    }
+    Ok(())
+}
+
+fn param_state<F: Float>(states: &mut HashMap<ParamId, ArrayD<F>>, id: ParamId, dim: IxDyn) -> &mut ArrayD<F> {
+    let state = states.entry(id).or_insert_with(|| ArrayD::zeros(dim.clone()));
+    if state.raw_dim() != dim {
+        *state = ArrayD::zeros(dim);
+    }
+    state
+}
+
+pub struct SGD<F: Float = f64> {
     pub learning_rate: f64,
     pub decay: f64,
     pub current_learning_rate: f64,
     pub iterations: i64,
-    pub momentum: f64
+    pub momentum: f64,
+    momentums: HashMap<ParamId, ArrayD<F>>
 }
 
-impl SGD {
+impl<F: Float> SGD<F> {
     pub fn new(learning_rate: f64, decay: f64, momentum: f64) -> Self {
         SGD{ 
-            learning_rate: learning_rate,
-            decay: decay,
+            learning_rate,
+            decay,
             current_learning_rate: learning_rate,
             iterations: 0,
-            momentum: momentum
+            momentum,
+            momentums: HashMap::new()
         }
     }
+}
 
-    pub fn pre_update_params(&mut self) {
+impl<F: Float> Optimizer<F> for SGD<F> {
+    fn pre_update_params(&mut self) {
         if self.decay != 0.0 {
             self.current_learning_rate = self.learning_rate * (
                 1.0 / (1.0 + self.decay * (self.iterations as f64))
@@ -31,59 +91,78 @@ impl SGD {
         }
     }
 
-    pub fn update_params(&self, layer: &mut Layer) {
-        let weight_updates;
-        let bias_updates;
-
-        if self.momentum != 0.0 {
-            if layer.weight_momentums.is_none() {
-                layer.weight_momentums = Some(Array2::zeros(layer.weights.dim()));
-                layer.bias_momentums = Some(Array1::zeros(layer.biases.dim()));
-            }
-
-            weight_updates = 
-                self.momentum * layer.weight_momentums() - self.current_learning_rate * layer.dweights();
-            layer.weight_momentums = Some(weight_updates.clone());
+    fn update_param(&mut self, param: Param<'_, F>) -> Result<()> {
+        let Param { id, mut values, grads, trainable } = param;
+        if !trainable {
+            return Ok(());
+        }
+        check_grads(&values, &grads)?;
 
-            bias_updates = 
-                self.momentum * layer.bias_momentums() - self.current_learning_rate * layer.dbiases();
-            layer.bias_momentums = Some(bias_updates.clone());
+        let learning_rate = F::cast(self.current_learning_rate);
+        let updates = if self.momentum != 0.0 {
+            let momentums = param_state(&mut self.momentums, id, values.raw_dim());
+            *momentums = &*momentums * F::cast(self.momentum) - &grads * learning_rate;
+            momentums.clone()
         }
 
         else {
-            weight_updates = -self.current_learning_rate * layer.dweights();
-            bias_updates = -self.current_learning_rate * layer.dbiases();
-        }
+            &grads * -learning_rate
+        };
 
-        layer.weights += &weight_updates;
-        layer.biases += &bias_updates;
+        values += &updates;
+        Ok(())
     }
 
-    pub fn post_update_params(&mut self) {
+    fn post_update_params(&mut self) {
         self.iterations += 1;
     }
+
+    fn current_learning_rate(&self) -> f64 {
+        self.current_learning_rate
+    }
+
+    fn set_hyperparams(&mut self, hyperparams: HashMap<&str, f64>) -> Result<()> {
+        check_keys(&hyperparams, &["learning_rate", "decay", "momentum"])?;
+
+        for (key, value) in hyperparams {
+            match key {
+                "learning_rate" => {
+                    self.learning_rate = value;
+                    self.current_learning_rate = value;
+                },
+                "decay" => self.decay = value,
+                "momentum" => self.momentum = value,
+                _ => unreachable!("Keys are checked above")
+            }
+        }
+        Ok(())
+    }
 }
 
-pub struct AdaGrad {
+pub struct AdaGrad<F: Float = f64> {
     pub learning_rate: f64,
     pub decay: f64,
     pub current_learning_rate: f64,
     pub iterations: i64,
-    pub epsilon: f64
+    pub epsilon: f64,
+    cache: HashMap<ParamId, ArrayD<F>>
 }
 
-impl AdaGrad {
+impl<F: Float> AdaGrad<F> {
     pub fn new(learning_rate: f64, decay: f64, epsilon: f64) -> Self {
         AdaGrad { 
-            learning_rate: learning_rate,
-            decay: decay,
+            learning_rate,
+            decay,
             current_learning_rate: learning_rate,
             iterations: 0,
-            epsilon: epsilon
+            epsilon,
+            cache: HashMap::new()
         }
     }
+}
 
-    pub fn pre_update_params(&mut self) {
+impl<F: Float> Optimizer<F> for AdaGrad<F> {
+    fn pre_update_params(&mut self) {
         if self.decay != 0.0 {
             self.current_learning_rate = self.learning_rate * (
                 1.0 / (1.0 + self.decay * (self.iterations as f64))
@@ -91,52 +170,73 @@ impl AdaGrad {
         }
     }
 
-    pub fn update_params(&self, layer: &mut Layer) {
-        if layer.weight_cache.is_none() {
-            layer.weight_cache = Some(Array2::zeros(layer.weights.dim()));
-            layer.bias_cache = Some(Array1::zeros(layer.biases.dim()))
+    fn update_param(&mut self, param: Param<'_, F>) -> Result<()> {
+        let Param { id, mut values, grads, trainable } = param;
+        if !trainable {
+            return Ok(());
         }
+        check_grads(&values, &grads)?;
 
-        let mut weight_cache = layer.weight_cache.clone().expect("weight_cache is not yet initialized");
-        let mut bias_cache = layer.bias_cache.clone().expect("bias_cache is not yet initialized");
-        let weight_pow = layer.dweights().mapv(|x| x.powi(2));
-        let bias_pow = layer.dbiases().mapv(|x| x.powi(2));
-        weight_cache += &weight_pow;
-        bias_cache += &bias_pow;
-        layer.weight_cache = Some(weight_cache.clone());
-        layer.bias_cache = Some(bias_cache.clone());
+        let cache = param_state(&mut self.cache, id, values.raw_dim());
+        *cache += &grads.mapv(|x| x.powi(2));
 
-        layer.weights += &(-self.current_learning_rate * layer.dweights() / (weight_cache.mapv(|x| x.sqrt()) + self.epsilon));
-        layer.biases += &(-self.current_learning_rate * layer.dbiases() / (bias_cache.mapv(|x| x.sqrt()) + self.epsilon));
+        let learning_rate = F::cast(self.current_learning_rate);
+        values += &(&grads * -learning_rate / (cache.mapv(|x| x.sqrt()) + F::cast(self.epsilon)));
+        Ok(())
     }
 
-    pub fn post_update_params(&mut self) {
+    fn post_update_params(&mut self) {
         self.iterations += 1;
     }
+
+    fn current_learning_rate(&self) -> f64 {
+        self.current_learning_rate
+    }
+
+    fn set_hyperparams(&mut self, hyperparams: HashMap<&str, f64>) -> Result<()> {
+        check_keys(&hyperparams, &["learning_rate", "decay", "epsilon"])?;
+
+        for (key, value) in hyperparams {
+            match key {
+                "learning_rate" => {
+                    self.learning_rate = value;
+                    self.current_learning_rate = value;
+                },
+                "decay" => self.decay = value,
+                "epsilon" => self.epsilon = value,
+                _ => unreachable!("Keys are checked above")
+            }
+        }
+        Ok(())
+    }
 }
 
-pub struct RMSProp {
+pub struct RMSProp<F: Float = f64> {
     pub learning_rate: f64,
     pub decay: f64,
     pub current_learning_rate: f64,
     pub iterations: i64,
     pub epsilon: f64,
-    pub rho: f64
+    pub rho: f64,
+    cache: HashMap<ParamId, ArrayD<F>>
 }
 
-impl RMSProp {
+impl<F: Float> RMSProp<F> {
     pub fn new(learning_rate: f64, decay: f64, epsilon: f64, rho: f64) -> Self {
         RMSProp {
-            learning_rate: learning_rate,
-            decay: decay,
+            learning_rate,
+            decay,
             iterations: 0,
             current_learning_rate: learning_rate,
-            epsilon: epsilon,
-            rho: rho
+            epsilon,
+            rho,
+            cache: HashMap::new()
         }
     }
+}
 
-    pub fn pre_update_params(&mut self) {
+impl<F: Float> Optimizer<F> for RMSProp<F> {
+    fn pre_update_params(&mut self) {
         if self.decay != 0.0 {
             self.current_learning_rate = self.learning_rate * (
                 1.0 / (1.0 + self.decay * (self.iterations as f64))
@@ -144,39 +244,62 @@ impl RMSProp {
         }
     }
 
-    pub fn update_params(&mut self, layer: &mut Layer) {
-        if layer.weight_cache.is_none() {
-            layer.weight_cache = Some(Array2::zeros(layer.weights.dim()));
-            layer.bias_cache = Some(Array1::zeros(layer.biases.dim()))
+    fn update_param(&mut self, param: Param<'_, F>) -> Result<()> {
+        let Param { id, mut values, grads, trainable } = param;
+        if !trainable {
+            return Ok(());
         }
+        check_grads(&values, &grads)?;
 
-        let mut weight_cache = layer.weight_cache.clone().expect("weight_cache not yet initialized");
-        let mut bias_cache = layer.bias_cache.clone().expect("bias_cache not yet initialized");
-        weight_cache = self.rho * weight_cache + (1.0 - self.rho) * layer.dweights().mapv(|x| x.powi(2));
-        bias_cache = self.rho * bias_cache + (1.0 - self.rho) * layer.dbiases().mapv(|x| x.powi(2));
-        layer.weight_cache = Some(weight_cache.clone());
-        layer.bias_cache = Some(bias_cache.clone());
+        let (rho, epsilon) = (F::cast(self.rho), F::cast(self.epsilon));
+        let cache = param_state(&mut self.cache, id, values.raw_dim());
+        *cache = &*cache * rho + grads.mapv(|x| x.powi(2)) * (F::one() - rho);
 
-        layer.weights += &(-self.current_learning_rate * layer.dweights() / (weight_cache.mapv(|x| x.sqrt() + self.epsilon)));
-        layer.biases += &(-self.current_learning_rate * layer.dbiases() / bias_cache.mapv(|x| x.sqrt() + self.epsilon));
+        let learning_rate = F::cast(self.current_learning_rate);
+        values += &(&grads * -learning_rate / cache.mapv(|x| x.sqrt() + epsilon));
+        Ok(())
     }
 
-    pub fn post_update_params(&mut self) {
+    fn post_update_params(&mut self) {
         self.iterations += 1;
     }
+
+    fn current_learning_rate(&self) -> f64 {
+        self.current_learning_rate
+    }
+
+    fn set_hyperparams(&mut self, hyperparams: HashMap<&str, f64>) -> Result<()> {
+        check_keys(&hyperparams, &["learning_rate", "decay", "epsilon", "rho"])?;
+
+        for (key, value) in hyperparams {
+            match key {
+                "learning_rate" => {
+                    self.learning_rate = value;
+                    self.current_learning_rate = value;
+                },
+                "decay" => self.decay = value,
+                "epsilon" => self.epsilon = value,
+                "rho" => self.rho = value,
+                _ => unreachable!("Keys are checked above")
+            }
+        }
+        Ok(())
+    }
 }
 
-pub struct Adam {
+pub struct Adam<F: Float = f64> {
     learning_rate: f64,
     current_learning_rate: f64,
     decay: f64,
     iterations: i64,
     epsilon: f64,
     beta_1: f64,
-    beta_2: f64
+    beta_2: f64,
+    momentums: HashMap<ParamId, ArrayD<F>>,
+    cache: HashMap<ParamId, ArrayD<F>>
 }
 
-impl Adam {
+impl<F: Float> Adam<F> {
     pub fn new() -> Self {
         Adam {
             learning_rate: 0.001,
@@ -185,11 +308,21 @@ impl Adam {
             iterations: 0,
             epsilon: 1e-7,
             beta_1: 0.9,
-            beta_2: 0.999
+            beta_2: 0.999,
+            momentums: HashMap::new(),
+            cache: HashMap::new()
         }
     }
+}
 
-    pub fn pre_update_params(&mut self) {
+impl<F: Float> Default for Adam<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Float> Optimizer<F> for Adam<F> {
+    fn pre_update_params(&mut self) {
         if self.decay != 0.0 {
             self.current_learning_rate = self.learning_rate * (
                 1.0 / (1.0 + self.decay * (self.iterations as f64))
@@ -197,56 +330,160 @@ impl Adam {
         }
     }
 
-    pub fn update_params(&mut self, layer: &mut Layer) {
-        if layer.weight_cache.is_none() {
-            layer.weight_momentums = Some(Array2::zeros(layer.weights.dim()));
-            layer.weight_cache = Some(Array2::zeros(layer.weights.dim()));
-            layer.bias_momentums = Some(Array1::zeros(layer.biases.dim()));
-            layer.bias_cache = Some(Array1::zeros(layer.biases.dim()));
+    fn update_param(&mut self, param: Param<'_, F>) -> Result<()> {
+        let Param { id, mut values, grads, trainable } = param;
+        if !trainable {
+            return Ok(());
         }
+        check_grads(&values, &grads)?;
 
-        layer.weight_momentums = Some(self.beta_1 * layer.weight_momentums() + (1.0 - self.beta_1) * layer.dweights());
-        layer.bias_momentums = Some(self.beta_1 * layer.bias_momentums() + (1.0 - self.beta_1) * layer.dbiases());
+        let (beta_1, beta_2) = (F::cast(self.beta_1), F::cast(self.beta_2));
 
-        let weight_momentums_corrected 
-            = layer.weight_momentums() / (1.0 - self.beta_1.powi(self.iterations as i32 + 1));
-        let bias_momentums_corrected
-            = layer.bias_momentums() / (1.0 - self.beta_1.powi(self.iterations as i32 + 1));
-        
-        layer.weight_cache = Some(
-            self.beta_2 * layer.weight_cache() + (1.0 - self.beta_2) * layer.dweights().mapv(|x| x.powi(2))
-        );
-        layer.bias_cache = Some(
-            self.beta_2 * layer.bias_cache() + (1.0 - self.beta_2) * layer.dbiases().mapv(|x| x.powi(2))
-        );
+        let momentums = param_state(&mut self.momentums, id, values.raw_dim());
+        *momentums = &*momentums * beta_1 + &grads * (F::one() - beta_1);
+        let momentums_corrected = &*momentums / F::cast(1.0 - self.beta_1.powi(self.iterations as i32 + 1));
 
-        let weight_cache_corrected 
-            = layer.weight_cache() / (1.0 - self.beta_2.powi(self.iterations as i32 + 1));
-        let bias_cahce_corrected
-            = layer.bias_cache() / (1.0 - self.beta_2.powi(self.iterations as i32 + 1));
+        let cache = param_state(&mut self.cache, id, values.raw_dim());
+        *cache = &*cache * beta_2 + grads.mapv(|x| x.powi(2)) * (F::one() - beta_2);
+        let cache_corrected = &*cache / F::cast(1.0 - self.beta_2.powi(self.iterations as i32 + 1));
 
-        layer.weights += 
-            &(-self.current_learning_rate * weight_momentums_corrected / 
-            (weight_cache_corrected.mapv(|x| x.sqrt()) + self.epsilon));
-        layer.biases +=
-            &(-self.current_learning_rate * bias_momentums_corrected / 
-            (bias_cahce_corrected.mapv(|x| x.sqrt()) + self.epsilon));
+        let learning_rate = F::cast(self.current_learning_rate);
+        values += 
+            &(momentums_corrected * -learning_rate / 
+            (cache_corrected.mapv(|x| x.sqrt()) + F::cast(self.epsilon)));
+        Ok(())
     }
 
-    pub fn post_update_params(&mut self) {
+    fn post_update_params(&mut self) {
         self.iterations += 1;
     }
 
-    pub fn set_hyperparams(&mut self, hyperparams: HashMap<&str, f64>) {
+    fn current_learning_rate(&self) -> f64 {
+        self.current_learning_rate
+    }
+
+    fn set_hyperparams(&mut self, hyperparams: HashMap<&str, f64>) -> Result<()> {
+        check_keys(&hyperparams, &["learning_rate", "decay", "epsilon", "beta_1", "beta_2"])?;
+
         for (key, value) in hyperparams {
             match key {
-                "learning_rate" => self.learning_rate = value,
+                "learning_rate" => {
+                    self.learning_rate = value;
+                    self.current_learning_rate = value;
+                },
                 "decay" => self.decay = value,
                 "epsilon" => self.epsilon = value,
                 "beta_1" => self.beta_1 = value,
                 "beta_2" => self.beta_2 = value,
-                _ => panic!("Invalid hyperparamter \"{}\" passed.", key)
+                _ => unreachable!("Keys are checked above")
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use ndarray::Array1;
+
+    use super::*;
+    use crate::{
+        activations::ReLU, 
+        datasets::spiral_data, 
+        layer::Layer, 
+        loss_functions::SoftmaxCategoricalCrossEntropy, 
+        model::{Model, Sequential}
+    };
+
+    #[test]
+    fn unknown_key_leaves_optimizer_unchanged() {
+        for name in ["sgd", "adagrad", "rmsprop", "adam"] {
+            let mut optimizer = optimizer_from_name::<f64>(name).unwrap();
+            let before = optimizer.current_learning_rate();
+
+            let result = optimizer.set_hyperparams(hashmap! { "learning_rate" => 0.123, "decay" => 0.5, "nope" => 1.0 });
+            assert!(matches!(result, Err(NnError::InvalidHyperparameter(_))), "{}", name);
+            assert_eq!(optimizer.current_learning_rate(), before, "{}", name);
+
+            optimizer.set_hyperparams(hashmap! { "learning_rate" => 0.123 }).unwrap();
+            assert_eq!(optimizer.current_learning_rate(), 0.123, "{}", name);
+        }
+    }
+
+    #[test]
+    fn switching_optimizers_starts_from_fresh_state() {
+        let (x, y) = spiral_data(20, 3);
+        let mut model = Sequential::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), optimizer_from_name("rmsprop").unwrap());
+        model.add(Layer::new(2, 8));
+        model.add(ReLU::new());
+        model.add(Layer::new(8, 3));
+        model.fit(&x, &y, 3, 16, None).unwrap();
+
+        model.optimizer = optimizer_from_name("adam").unwrap();
+        let history = model.fit(&x, &y, 3, 16, None).unwrap();
+        assert!(history.loss.iter().all(|loss| loss.is_finite()));
+    }
+
+    #[test]
+    fn state_is_kept_per_parameter() {
+        // One optimizer stepping two differently shaped layers must keep separate state for each.
+        let mut optimizer: Adam = Adam::new();
+        let mut small = Layer::new(2, 3);
+        let mut large = Layer::new(4, 5);
+        for _ in 0..2 {
+            for layer in [&mut small, &mut large] {
+                let inputs = ArrayD::ones(vec![2, layer.weights.nrows()]);
+                layer.forward(&inputs).unwrap();
+                layer.backward(&ArrayD::ones(vec![2, layer.weights.ncols()])).unwrap();
+                optimizer.update_params(layer).unwrap();
+            }
+        }
+        assert_eq!(optimizer.momentums.len(), 4);
+        let mut sizes: Vec<usize> = optimizer.cache.values().map(|cache| cache.len()).collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, [3, 5, 6, 20]);
+    }
+
+    #[test]
+    fn frozen_layers_survive_fit_with_every_optimizer() {
+        let (x, y) = spiral_data(20, 3);
+        for name in ["sgd", "adagrad", "rmsprop", "adam"] {
+            let mut frozen = Layer::new(2, 8);
+            frozen.biases = Some(Array1::from_elem(8, 0.1));
+            frozen.set_trainable(false);
+            let (frozen_weights, frozen_biases) = (frozen.weights.clone(), frozen.biases.clone().unwrap());
+            let trained = Layer::new(8, 3);
+            let trained_weights = trained.weights.clone();
+
+            let mut model = Sequential::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), optimizer_from_name(name).unwrap());
+            model.add(frozen);
+            model.add(ReLU::new());
+            model.add(trained);
+            model.fit(&x, &y, 3, 16, None).unwrap();
+
+            let frozen_params = model.layers[0].params().unwrap();
+            assert_eq!(frozen_params[0].values, frozen_weights.view().into_dyn(), "{}", name);
+            assert_eq!(frozen_params[1].values, frozen_biases.view().into_dyn(), "{}", name);
+            drop(frozen_params);
+            assert_ne!(model.layers[2].params().unwrap()[0].values, trained_weights.view().into_dyn(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn update_param_skips_frozen_params() {
+        let mut layer = Layer::new(2, 3);
+        layer.forward(&ArrayD::ones(vec![2, 2])).unwrap();
+        layer.backward(&ArrayD::ones(vec![2, 3])).unwrap();
+        layer.set_trainable(false);
+        let weights = layer.weights.clone();
+
+        for name in ["sgd", "adagrad", "rmsprop", "adam"] {
+            let mut optimizer = optimizer_from_name::<f64>(name).unwrap();
+            for param in layer.params().unwrap() {
+                optimizer.update_param(param).unwrap();
             }
+            assert_eq!(layer.weights, weights, "{}", name);
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,406 @@
+use ndarray::{Array2, ArrayD, Axis, Ix4};
+
+use crate::{
+    convolution::Geometry, 
+    error::{NnError, Result}, 
+    float::Float, 
+    module::Module, 
+    utils::{check_shape, reshape, to_rank}
+};
+
+// Flat input indices (within one sample) covered by the window of output position (c, oh, ow).
+fn window(geometry: &Geometry, c: usize, oh: usize, ow: usize) -> impl Iterator<Item = usize> + '_ {
+    let (kh, kw) = geometry.kernel;
+    (0..kh).flat_map(move |i| (0..kw).filter_map(move |j| {
+        let h = geometry.source(oh, i, geometry.stride.0, 1, 0, geometry.height)?;
+        let w = geometry.source(ow, j, geometry.stride.1, 1, 0, geometry.width)?;
+        Some((c * geometry.height + h) * geometry.width + w)
+    }))
+}
+
+fn pool_geometry(input_shape: (usize, usize, usize), pool_size: (usize, usize), stride: (usize, usize)) -> Result<Geometry> {
+    Geometry::new(input_shape, pool_size, stride, (1, 1), (0, 0), (0, 0))
+}
+
+// Shape of a batch of pooled maps for `geometry`.
+fn pooled_shape(geometry: &Geometry, n_samples: usize) -> [usize; 4] {
+    [n_samples, geometry.channels, geometry.out_height, geometry.out_width]
+}
+
+/// Max pooling over (batch, channels, height, width) images; gradients are routed to the argmax of each window.
+pub struct MaxPool2D<F: Float = f64> {
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    geometry: Geometry,
+    argmax: Option<Array2<usize>>
+}
+
+impl<F: Float> MaxPool2D<F> {
+    /// `input_shape` is (channels, height, width).
+    pub fn new(input_shape: (usize, usize, usize), pool_size: (usize, usize), stride: (usize, usize)) -> Result<Self> {
+        Ok(MaxPool2D {
+            outputs: None,
+            dinputs: None,
+            geometry: pool_geometry(input_shape, pool_size, stride)?,
+            argmax: None
+        })
+    }
+
+    /// Shape (channels, height, width) of one output sample.
+    pub fn output_shape(&self) -> (usize, usize, usize) {
+        (self.geometry.channels, self.geometry.out_height, self.geometry.out_width)
+    }
+}
+
+impl<F: Float> Module<F> for MaxPool2D<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let g = &self.geometry;
+        let rows = g.image_rows(inputs)?;
+
+        let n_outputs = g.channels * g.output_positions();
+        let mut outputs = Array2::zeros((rows.nrows(), n_outputs));
+        let mut argmax = Array2::zeros((rows.nrows(), n_outputs));
+
+        for (sample, x) in rows.outer_iter().enumerate() {
+            for c in 0..g.channels {
+                for oh in 0..g.out_height {
+                    for ow in 0..g.out_width {
+                        let out = (c * g.out_height + oh) * g.out_width + ow;
+                        let best = window(g, c, oh, ow)
+                            .reduce(|best, i| if x[i] > x[best] { i } else { best })
+                            .expect("Pooling windows are never empty");
+                        outputs[[sample, out]] = x[best];
+                        argmax[[sample, out]] = best;
+                    }
+                }
+            }
+        }
+
+        self.outputs = Some(reshape(outputs, &pooled_shape(g, rows.nrows())));
+        self.argmax = Some(argmax);
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+        let argmax = self.argmax.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })?;
+
+        let g = &self.geometry;
+        let mut dinputs = Array2::zeros((argmax.nrows(), g.input_size()));
+        for (((sample, _), &source), &dvalue) in argmax.indexed_iter().zip(dvalues.iter()) {
+            dinputs[[sample, source]] += dvalue;
+        }
+        self.dinputs = Some(reshape(dinputs, &[argmax.nrows(), g.channels, g.height, g.width]));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+}
+
+/// Average pooling over (batch, channels, height, width) images.
+pub struct AvgPool2D<F: Float = f64> {
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    geometry: Geometry
+}
+
+impl<F: Float> AvgPool2D<F> {
+    /// `input_shape` is (channels, height, width).
+    pub fn new(input_shape: (usize, usize, usize), pool_size: (usize, usize), stride: (usize, usize)) -> Result<Self> {
+        Ok(AvgPool2D {
+            outputs: None,
+            dinputs: None,
+            geometry: pool_geometry(input_shape, pool_size, stride)?
+        })
+    }
+
+    /// Shape (channels, height, width) of one output sample.
+    pub fn output_shape(&self) -> (usize, usize, usize) {
+        (self.geometry.channels, self.geometry.out_height, self.geometry.out_width)
+    }
+
+    fn window_scale(&self) -> F {
+        F::cast(1.0 / (self.geometry.kernel.0 * self.geometry.kernel.1) as f64)
+    }
+}
+
+impl<F: Float> Module<F> for AvgPool2D<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let g = &self.geometry;
+        let rows = g.image_rows(inputs)?;
+
+        let scale = self.window_scale();
+        let mut outputs = Array2::zeros((rows.nrows(), g.channels * g.output_positions()));
+
+        for (sample, x) in rows.outer_iter().enumerate() {
+            for c in 0..g.channels {
+                for oh in 0..g.out_height {
+                    for ow in 0..g.out_width {
+                        let out = (c * g.out_height + oh) * g.out_width + ow;
+                        outputs[[sample, out]] = window(g, c, oh, ow).map(|i| x[i]).sum::<F>() * scale;
+                    }
+                }
+            }
+        }
+
+        self.outputs = Some(reshape(outputs, &pooled_shape(g, rows.nrows())));
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+
+        let g = &self.geometry;
+        let scale = self.window_scale();
+        let n_samples = dvalues.len_of(Axis(0));
+        let mut dinputs = Array2::zeros((n_samples, g.input_size()));
+
+        for ((sample, c, oh, ow), &dvalue) in to_rank::<F, Ix4>(dvalues)?.indexed_iter() {
+            for i in window(g, c, oh, ow) {
+                dinputs[[sample, i]] += dvalue * scale;
+            }
+        }
+
+        self.dinputs = Some(reshape(dinputs, &[n_samples, g.channels, g.height, g.width]));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+}
+
+// Views (batch, channels, height, width) `inputs` as (batch, channels, height * width).
+fn channel_maps<F: Float>(inputs: &ArrayD<F>, (channels, height, width): (usize, usize, usize)) -> Result<Array2<F>> {
+    let n_samples = to_rank::<F, Ix4>(inputs)?.len_of(Axis(0));
+    check_shape(inputs, &[n_samples, channels, height, width])?;
+    Ok(reshape(inputs.clone(), &[n_samples * channels, height * width])
+        .into_dimensionality()
+        .expect("Reshaped to two axes"))
+}
+
+/// Averages every channel of (batch, channels, height, width) images down to one value, giving (batch, channels) outputs.
+pub struct GlobalAvgPool2D<F: Float = f64> {
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    input_shape: (usize, usize, usize)
+}
+
+impl<F: Float> GlobalAvgPool2D<F> {
+    /// `input_shape` is (channels, height, width).
+    pub fn new(input_shape: (usize, usize, usize)) -> Self {
+        GlobalAvgPool2D {
+            outputs: None,
+            dinputs: None,
+            input_shape
+        }
+    }
+}
+
+impl<F: Float> Module<F> for GlobalAvgPool2D<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let maps = channel_maps(inputs, self.input_shape)?;
+        let means = maps.mean_axis(Axis(1)).ok_or(NnError::EmptyBatch)?;
+        self.outputs = Some(reshape(means, &[inputs.len_of(Axis(0)), self.input_shape.0]));
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+
+        let (channels, height, width) = self.input_shape;
+        let scale = F::cast(1.0 / (height * width) as f64);
+        let n_samples = dvalues.len_of(Axis(0));
+        let dinputs = dvalues
+            .clone()
+            .insert_axis(Axis(2))
+            .insert_axis(Axis(3))
+            .broadcast(vec![n_samples, channels, height, width])
+            .expect("Per-channel gradients broadcast over each map")
+            .mapv(|dvalue| dvalue * scale);
+
+        self.dinputs = Some(dinputs);
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+}
+
+/// Takes the maximum of every channel of (batch, channels, height, width) images, giving (batch, channels) outputs.
+pub struct GlobalMaxPool2D<F: Float = f64> {
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    input_shape: (usize, usize, usize),
+    // Position of the maximum within each (sample, channel) map.
+    argmax: Vec<usize>
+}
+
+impl<F: Float> GlobalMaxPool2D<F> {
+    /// `input_shape` is (channels, height, width).
+    pub fn new(input_shape: (usize, usize, usize)) -> Self {
+        GlobalMaxPool2D {
+            outputs: None,
+            dinputs: None,
+            input_shape,
+            argmax: Vec::new()
+        }
+    }
+}
+
+impl<F: Float> Module<F> for GlobalMaxPool2D<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let (channels, height, width) = self.input_shape;
+        let maps = channel_maps(inputs, self.input_shape)?;
+
+        let mut argmax = Vec::with_capacity(maps.nrows());
+        for map in maps.outer_iter() {
+            let best = (0..map.len())
+                .reduce(|best, i| if map[i] > map[best] { i } else { best })
+                .ok_or(NnError::ShapeMismatch { expected: vec![channels, 1, 1], found: vec![channels, height, width] })?;
+            argmax.push(best);
+        }
+
+        let maxima = maps.outer_iter().zip(&argmax).map(|(map, &best)| map[best]).collect();
+        self.outputs = Some(reshape(Array2::from_shape_vec((maps.nrows(), 1), maxima).expect("One maximum per map"), &[inputs.len_of(Axis(0)), channels]));
+        self.argmax = argmax;
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+
+        let (channels, height, width) = self.input_shape;
+        let n_samples = dvalues.len_of(Axis(0));
+        let mut dinputs = Array2::zeros((n_samples * channels, height * width));
+        for ((mut map, &best), &dvalue) in dinputs.outer_iter_mut().zip(&self.argmax).zip(dvalues.iter()) {
+            map[best] = dvalue;
+        }
+
+        self.dinputs = Some(reshape(dinputs, &[n_samples, channels, height, width]));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+}
+
+/// Bridges feature maps or sequences into a dense `Layer` by flattening every axis after the batch
+/// axis, so (batch, d1, d2, ...) inputs give (batch, d1 * d2 * ...) outputs.
+pub struct Flatten<F: Float = f64> {
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    input_shape: Vec<usize>
+}
+
+impl<F: Float> Flatten<F> {
+    pub fn new() -> Self {
+        Flatten {
+            outputs: None,
+            dinputs: None,
+            input_shape: Vec::new()
+        }
+    }
+}
+
+impl<F: Float> Default for Flatten<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Float> Module<F> for Flatten<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        if inputs.ndim() == 0 {
+            return Err(NnError::RankMismatch { expected: 1, found: 0 });
+        }
+
+        let n_samples = inputs.len_of(Axis(0));
+        self.outputs = Some(reshape(inputs.clone(), &[n_samples, inputs.shape()[1..].iter().product()]));
+        self.input_shape = inputs.shape().to_vec();
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+        self.dinputs = Some(reshape(dvalues.clone(), &self.input_shape));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{Array1, Array4};
+
+    use super::*;
+    use crate::testing::{assert_gradients, random};
+
+    fn image() -> ArrayD<f64> {
+        Array1::range(1.0, 17.0, 1.0).into_shape((1, 1, 4, 4)).unwrap().into_dyn()
+    }
+
+    #[test]
+    fn pooling_gradients() {
+        let inputs = random(&[3, 2, 5, 6], 1);
+        assert_gradients(&mut MaxPool2D::new((2, 5, 6), (2, 2), (2, 2)).unwrap(), &inputs);
+        assert_gradients(&mut AvgPool2D::new((2, 5, 6), (3, 2), (1, 2)).unwrap(), &inputs);
+        assert_gradients(&mut GlobalAvgPool2D::new((2, 5, 6)), &inputs);
+        assert_gradients(&mut GlobalMaxPool2D::new((2, 5, 6)), &inputs);
+        assert_gradients(&mut Flatten::new(), &inputs);
+    }
+
+    #[test]
+    fn pooling_values() {
+        let mut max_pool = MaxPool2D::new((1, 4, 4), (2, 2), (2, 2)).unwrap();
+        max_pool.forward(&image()).unwrap();
+        let expected = Array4::from_shape_vec((1, 1, 2, 2), vec![6.0, 8.0, 14.0, 16.0]).unwrap().into_dyn();
+        assert_eq!(max_pool.outputs().unwrap(), &expected);
+
+        let mut avg_pool = AvgPool2D::new((1, 4, 4), (2, 2), (2, 2)).unwrap();
+        avg_pool.forward(&image()).unwrap();
+        let expected = Array4::from_shape_vec((1, 1, 2, 2), vec![3.5, 5.5, 11.5, 13.5]).unwrap().into_dyn();
+        assert_eq!(avg_pool.outputs().unwrap(), &expected);
+
+        let mut global_max = GlobalMaxPool2D::new((1, 4, 4));
+        global_max.forward(&image()).unwrap();
+        assert_eq!(global_max.outputs().unwrap().shape(), &[1, 1]);
+        assert_eq!(global_max.outputs().unwrap()[[0, 0]], 16.0);
+
+        let mut flatten = Flatten::new();
+        flatten.forward(&random(&[3, 2, 5, 6], 2)).unwrap();
+        assert_eq!(flatten.outputs().unwrap().shape(), &[3, 60]);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+fn global_rng() -> &'static Mutex<StdRng> {
+    static RNG: OnceLock<Mutex<StdRng>> = OnceLock::new();
+    RNG.get_or_init(|| Mutex::new(StdRng::from_entropy()))
+}
+
+/// Reseeds the crate-wide generator behind weight initialization, datasets, shuffling and dropout.
+/// Runs are reproducible as long as the random draws happen in the same order, i.e. from a single thread.
+pub fn set_seed(seed: u64) {
+    *global_rng().lock().unwrap_or_else(PoisonError::into_inner) = StdRng::seed_from_u64(seed);
+}
+
+/// Runs `f` with exclusive access to the crate-wide generator.
+pub fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    let mut rng = global_rng().lock().unwrap_or_else(PoisonError::into_inner);
+    f(&mut rng)
+}
\ No newline at end of file
@@ -0,0 +1,879 @@
+use ndarray::{concatenate, s, stack, Array1, Array2, ArrayBase, ArrayD, ArrayView2, Axis, CowArray, Data, Ix2, Ix3, Slice};
+
+use crate::{
+    error::{NnError, Result}, 
+    float::Float, 
+    initializers::Initializer, 
+    module::{Module, Param, ParamId}, 
+    utils::{check_shape, reshape, to_rank}
+};
+
+/// Introspection shared by the recurrent layers, which lets `Bidirectional` wrap any of them.
+pub trait Recurrent<F: Float = f64>: Module<F> {
+    fn n_features(&self) -> usize;
+    fn units(&self) -> usize;
+    fn return_sequences(&self) -> bool;
+}
+
+/// How `Bidirectional` combines the outputs of its two directions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Merge {
+    /// Joins the directions feature-wise at every step, doubling the units.
+    Concat,
+    Sum
+}
+
+// Flattens (batch, time, n_features) `inputs` into rows of `time * n_features` values, returning the number of steps.
+// Sequences without any step are rejected: there would be no state to output.
+fn sequence_rows<F: Float>(inputs: &ArrayD<F>, n_features: usize) -> Result<(CowArray<'_, F, Ix2>, usize)> {
+    let (n_samples, steps, _) = to_rank::<F, Ix3>(inputs)?.dim();
+    check_shape(inputs, &[n_samples, steps, n_features])?;
+    if steps == 0 {
+        return Err(NnError::EmptyBatch);
+    }
+    Ok((inputs.to_shape((n_samples, steps * n_features)).expect("Element count is unchanged"), steps))
+}
+
+fn step<F: Float, S: Data<Elem = F>>(values: &ArrayBase<S, Ix2>, t: usize, width: usize) -> ArrayView2<'_, F> {
+    values.slice(s![.., t * width..(t + 1) * width])
+}
+
+fn sigmoid<F: Float>(x: F) -> F {
+    F::one() / (F::one() + (-x).exp())
+}
+
+// Reverses the time axis (axis 1).
+fn reverse_time<F: Float>(values: &ArrayD<F>) -> ArrayD<F> {
+    values.slice_axis(Axis(1), Slice::new(0, None, -1)).as_standard_layout().into_owned()
+}
+
+// Every hidden state after the initial one as (batch, time, units) when returning sequences, otherwise the last.
+fn sequence_outputs<F: Float>(states: &[Array2<F>], return_sequences: bool) -> ArrayD<F> {
+    if return_sequences {
+        let views: Vec<_> = states[1..].iter().map(|state| state.view()).collect();
+        stack(Axis(1), &views).expect("Hidden states share their batch size").into_dyn()
+    }
+
+    else {
+        states[states.len() - 1].clone().into_dyn()
+    }
+}
+
+// Checks `dvalues` against the outputs and returns the gradient arriving at each time step.
+fn step_gradients<F: Float>(dvalues: &ArrayD<F>, outputs: &ArrayD<F>, steps: usize, units: usize, return_sequences: bool) -> Result<Vec<Array2<F>>> {
+    check_shape(dvalues, outputs.shape())?;
+    let n_samples = dvalues.len_of(Axis(0));
+
+    Ok((0..steps).map(|t| {
+        if return_sequences {
+            dvalues.index_axis(Axis(1), t).to_owned().into_dimensionality().expect("Sequence gradients have three axes")
+        }
+
+        else if t == steps - 1 {
+            dvalues.clone().into_dimensionality().expect("Last-state gradients have two axes")
+        }
+
+        else {
+            Array2::zeros((n_samples, units))
+        }
+    }).collect())
+}
+
+// Whether the hidden-state gradient stops at step `t` instead of flowing on to `t - 1`. Chunks are counted
+// back from the last step, so the final state always backpropagates through `bptt_steps` steps.
+fn truncated_at(t: usize, steps: usize, bptt_steps: Option<usize>) -> Result<bool> {
+    match bptt_steps {
+        Some(0) => Err(NnError::InvalidHyperparameter("bptt_steps must be positive".to_string())),
+        Some(k) => Ok((steps - t).is_multiple_of(k)),
+        None => Ok(false)
+    }
+}
+
+/// Elman recurrent layer, `h_t = tanh(x_t W_x + h_{t-1} W_h + b)`, over (batch, time, n_features) sequences.
+pub struct RNN<F: Float = f64> {
+    pub weights_input: Array2<F>,
+    pub weights_hidden: Array2<F>,
+    pub biases: Array1<F>,
+    /// Output every hidden state as (batch, time, units) instead of only the last one as (batch, units).
+    pub return_sequences: bool,
+    /// Truncated backpropagation through time: gradients only flow back within chunks of this many steps,
+    /// counted back from the last step.
+    pub bptt_steps: Option<usize>,
+    pub weights_input_trainable: bool,
+    pub weights_hidden_trainable: bool,
+    pub biases_trainable: bool,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dweights_input: Option<Array2<F>>,
+    pub dweights_hidden: Option<Array2<F>>,
+    pub dbiases: Option<Array1<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    inputs: Option<Array2<F>>,
+    states: Vec<Array2<F>>,
+    weights_input_id: ParamId,
+    weights_hidden_id: ParamId,
+    biases_id: ParamId
+}
+
+impl<F: Float> RNN<F> {
+    pub fn new(n_features: usize, units: usize, return_sequences: bool) -> Self {
+        RNN {
+            weights_input: Initializer::XavierUniform.initialize(n_features, units),
+            weights_hidden: Initializer::Orthogonal(1.0).initialize(units, units),
+            biases: Array1::zeros(units),
+            return_sequences,
+            bptt_steps: None,
+            weights_input_trainable: true,
+            weights_hidden_trainable: true,
+            biases_trainable: true,
+            outputs: None,
+            dweights_input: None,
+            dweights_hidden: None,
+            dbiases: None,
+            dinputs: None,
+            inputs: None,
+            states: Vec::new(),
+            weights_input_id: ParamId::unique(),
+            weights_hidden_id: ParamId::unique(),
+            biases_id: ParamId::unique()
+        }
+    }
+}
+
+impl<F: Float> Module<F> for RNN<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let (n_features, units) = self.weights_input.dim();
+        let (inputs, steps) = sequence_rows(inputs, n_features)?;
+
+        let mut states = vec![Array2::zeros((inputs.nrows(), units))];
+        for t in 0..steps {
+            let z = step(&inputs, t, n_features).dot(&self.weights_input) 
+                + states[t].dot(&self.weights_hidden) 
+                + &self.biases;
+            states.push(z.mapv(|v| v.tanh()));
+        }
+
+        self.outputs = Some(sequence_outputs(&states, self.return_sequences));
+        self.states = states;
+        self.inputs = Some(inputs.into_owned());
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let inputs = self.inputs.as_ref().ok_or(NnError::CallOrder { missing: "inputs", call: "forward" })?;
+        let (n_features, units) = self.weights_input.dim();
+        let steps = self.states.len() - 1;
+        let dsteps = step_gradients(dvalues, self.outputs()?, steps, units, self.return_sequences)?;
+
+        let mut dweights_input = Array2::zeros(self.weights_input.raw_dim());
+        let mut dweights_hidden = Array2::zeros(self.weights_hidden.raw_dim());
+        let mut dbiases = Array1::zeros(units);
+        let mut dinputs = Array2::zeros(inputs.raw_dim());
+        let mut dnext = Array2::zeros((inputs.nrows(), units));
+
+        for t in (0..steps).rev() {
+            let dh = &dnext + &dsteps[t];
+            let dz = dh * self.states[t + 1].mapv(|h| F::one() - h * h);
+
+            dweights_input += &step(inputs, t, n_features).t().dot(&dz);
+            dweights_hidden += &self.states[t].t().dot(&dz);
+            dbiases += &dz.sum_axis(Axis(0));
+            dinputs.slice_mut(s![.., t * n_features..(t + 1) * n_features]).assign(&dz.dot(&self.weights_input.t()));
+
+            if truncated_at(t, steps, self.bptt_steps)? {
+                dnext.fill(F::zero());
+            }
+
+            else {
+                dnext = dz.dot(&self.weights_hidden.t());
+            }
+        }
+
+        self.dweights_input = Some(dweights_input);
+        self.dweights_hidden = Some(dweights_hidden);
+        self.dbiases = Some(dbiases);
+        self.dinputs = Some(reshape(dinputs, &[inputs.nrows(), steps, n_features]));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dweights_input = self.dweights_input.as_ref().ok_or(NnError::CallOrder { missing: "dweights_input", call: "backward" })?;
+        let dweights_hidden = self.dweights_hidden.as_ref().ok_or(NnError::CallOrder { missing: "dweights_hidden", call: "backward" })?;
+        let dbiases = self.dbiases.as_ref().ok_or(NnError::CallOrder { missing: "dbiases", call: "backward" })?;
+
+        Ok(vec![
+            Param { 
+                id: self.weights_input_id, 
+                values: self.weights_input.view_mut().into_dyn(), 
+                grads: dweights_input.view().into_dyn(), 
+                trainable: self.weights_input_trainable 
+            },
+            Param { 
+                id: self.weights_hidden_id, 
+                values: self.weights_hidden.view_mut().into_dyn(), 
+                grads: dweights_hidden.view().into_dyn(), 
+                trainable: self.weights_hidden_trainable 
+            },
+            Param { 
+                id: self.biases_id, 
+                values: self.biases.view_mut().into_dyn(), 
+                grads: dbiases.view().into_dyn(), 
+                trainable: self.biases_trainable 
+            }
+        ])
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.weights_input_trainable = trainable;
+        self.weights_hidden_trainable = trainable;
+        self.biases_trainable = trainable;
+    }
+}
+
+/// Long short-term memory layer over (batch, time, n_features) sequences.
+/// Gate weights are stacked column-wise in input, forget, cell, output order.
+pub struct LSTM<F: Float = f64> {
+    /// Shape (n_features, 4 * units).
+    pub weights_input: Array2<F>,
+    /// Shape (units, 4 * units).
+    pub weights_hidden: Array2<F>,
+    /// Length 4 * units; the forget gate biases start at one.
+    pub biases: Array1<F>,
+    /// Output every hidden state as (batch, time, units) instead of only the last one as (batch, units).
+    pub return_sequences: bool,
+    /// Truncated backpropagation through time: gradients only flow back within chunks of this many steps,
+    /// counted back from the last step.
+    pub bptt_steps: Option<usize>,
+    pub weights_input_trainable: bool,
+    pub weights_hidden_trainable: bool,
+    pub biases_trainable: bool,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dweights_input: Option<Array2<F>>,
+    pub dweights_hidden: Option<Array2<F>>,
+    pub dbiases: Option<Array1<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    inputs: Option<Array2<F>>,
+    states: Vec<Array2<F>>,
+    cells: Vec<Array2<F>>,
+    // Activated gates of every step, shaped like the stacked pre-activations.
+    gates: Vec<Array2<F>>,
+    weights_input_id: ParamId,
+    weights_hidden_id: ParamId,
+    biases_id: ParamId
+}
+
+impl<F: Float> LSTM<F> {
+    pub fn new(n_features: usize, units: usize, return_sequences: bool) -> Self {
+        let mut biases = Array1::zeros(4 * units);
+        biases.slice_mut(s![units..2 * units]).fill(F::one());
+
+        LSTM {
+            weights_input: Initializer::XavierUniform.sample((n_features, 4 * units), n_features, units),
+            weights_hidden: Initializer::Orthogonal(1.0).initialize(units, 4 * units),
+            biases,
+            return_sequences,
+            bptt_steps: None,
+            weights_input_trainable: true,
+            weights_hidden_trainable: true,
+            biases_trainable: true,
+            outputs: None,
+            dweights_input: None,
+            dweights_hidden: None,
+            dbiases: None,
+            dinputs: None,
+            inputs: None,
+            states: Vec::new(),
+            cells: Vec::new(),
+            gates: Vec::new(),
+            weights_input_id: ParamId::unique(),
+            weights_hidden_id: ParamId::unique(),
+            biases_id: ParamId::unique()
+        }
+    }
+}
+
+impl<F: Float> Module<F> for LSTM<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let (n_features, units) = (self.weights_input.nrows(), self.units());
+        let (inputs, steps) = sequence_rows(inputs, n_features)?;
+
+        let mut states = vec![Array2::zeros((inputs.nrows(), units))];
+        let mut cells = vec![Array2::zeros((inputs.nrows(), units))];
+        let mut gates = Vec::with_capacity(steps);
+        for t in 0..steps {
+            let mut z = step(&inputs, t, n_features).dot(&self.weights_input) 
+                + states[t].dot(&self.weights_hidden) 
+                + &self.biases;
+            z.slice_mut(s![.., ..2 * units]).mapv_inplace(sigmoid);
+            z.slice_mut(s![.., 2 * units..3 * units]).mapv_inplace(|v| v.tanh());
+            z.slice_mut(s![.., 3 * units..]).mapv_inplace(sigmoid);
+
+            let (i, f) = (step(&z, 0, units), step(&z, 1, units));
+            let (g, o) = (step(&z, 2, units), step(&z, 3, units));
+            let cell = &f * &cells[t] + &i * &g;
+            states.push(&o * &cell.mapv(|c| c.tanh()));
+            cells.push(cell);
+            gates.push(z);
+        }
+
+        self.outputs = Some(sequence_outputs(&states, self.return_sequences));
+        self.states = states;
+        self.cells = cells;
+        self.gates = gates;
+        self.inputs = Some(inputs.into_owned());
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let inputs = self.inputs.as_ref().ok_or(NnError::CallOrder { missing: "inputs", call: "forward" })?;
+        let (n_features, units) = (self.weights_input.nrows(), self.units());
+        let steps = self.gates.len();
+        let dsteps = step_gradients(dvalues, self.outputs()?, steps, units, self.return_sequences)?;
+
+        let mut dweights_input = Array2::zeros(self.weights_input.raw_dim());
+        let mut dweights_hidden = Array2::zeros(self.weights_hidden.raw_dim());
+        let mut dbiases = Array1::zeros(4 * units);
+        let mut dinputs = Array2::zeros(inputs.raw_dim());
+        let mut dnext = Array2::zeros((inputs.nrows(), units));
+        let mut dcell_next = Array2::zeros((inputs.nrows(), units));
+
+        for t in (0..steps).rev() {
+            let gates = &self.gates[t];
+            let (i, f) = (step(gates, 0, units), step(gates, 1, units));
+            let (g, o) = (step(gates, 2, units), step(gates, 3, units));
+            let tanh_cell = self.cells[t + 1].mapv(|c| c.tanh());
+
+            let dh = &dnext + &dsteps[t];
+            let dcell = &dcell_next + &(&dh * &o * tanh_cell.mapv(|c| F::one() - c * c));
+
+            let mut dz = Array2::zeros(gates.raw_dim());
+            dz.slice_mut(s![.., ..units]).assign(&(&dcell * &g * i.mapv(|v| v * (F::one() - v))));
+            dz.slice_mut(s![.., units..2 * units]).assign(&(&dcell * &self.cells[t] * f.mapv(|v| v * (F::one() - v))));
+            dz.slice_mut(s![.., 2 * units..3 * units]).assign(&(&dcell * &i * g.mapv(|v| F::one() - v * v)));
+            dz.slice_mut(s![.., 3 * units..]).assign(&(&dh * &tanh_cell * o.mapv(|v| v * (F::one() - v))));
+
+            dweights_input += &step(inputs, t, n_features).t().dot(&dz);
+            dweights_hidden += &self.states[t].t().dot(&dz);
+            dbiases += &dz.sum_axis(Axis(0));
+            dinputs.slice_mut(s![.., t * n_features..(t + 1) * n_features]).assign(&dz.dot(&self.weights_input.t()));
+
+            if truncated_at(t, steps, self.bptt_steps)? {
+                dnext.fill(F::zero());
+                dcell_next.fill(F::zero());
+            }
+
+            else {
+                dnext = dz.dot(&self.weights_hidden.t());
+                dcell_next = dcell * f;
+            }
+        }
+
+        self.dweights_input = Some(dweights_input);
+        self.dweights_hidden = Some(dweights_hidden);
+        self.dbiases = Some(dbiases);
+        self.dinputs = Some(reshape(dinputs, &[inputs.nrows(), steps, n_features]));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dweights_input = self.dweights_input.as_ref().ok_or(NnError::CallOrder { missing: "dweights_input", call: "backward" })?;
+        let dweights_hidden = self.dweights_hidden.as_ref().ok_or(NnError::CallOrder { missing: "dweights_hidden", call: "backward" })?;
+        let dbiases = self.dbiases.as_ref().ok_or(NnError::CallOrder { missing: "dbiases", call: "backward" })?;
+
+        Ok(vec![
+            Param { 
+                id: self.weights_input_id, 
+                values: self.weights_input.view_mut().into_dyn(), 
+                grads: dweights_input.view().into_dyn(), 
+                trainable: self.weights_input_trainable 
+            },
+            Param { 
+                id: self.weights_hidden_id, 
+                values: self.weights_hidden.view_mut().into_dyn(), 
+                grads: dweights_hidden.view().into_dyn(), 
+                trainable: self.weights_hidden_trainable 
+            },
+            Param { 
+                id: self.biases_id, 
+                values: self.biases.view_mut().into_dyn(), 
+                grads: dbiases.view().into_dyn(), 
+                trainable: self.biases_trainable 
+            }
+        ])
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.weights_input_trainable = trainable;
+        self.weights_hidden_trainable = trainable;
+        self.biases_trainable = trainable;
+    }
+}
+
+impl<F: Float> Recurrent<F> for RNN<F> {
+    fn n_features(&self) -> usize {
+        self.weights_input.nrows()
+    }
+
+    fn units(&self) -> usize {
+        self.weights_hidden.nrows()
+    }
+
+    fn return_sequences(&self) -> bool {
+        self.return_sequences
+    }
+}
+
+impl<F: Float> Recurrent<F> for LSTM<F> {
+    fn n_features(&self) -> usize {
+        self.weights_input.nrows()
+    }
+
+    fn units(&self) -> usize {
+        self.weights_hidden.nrows()
+    }
+
+    fn return_sequences(&self) -> bool {
+        self.return_sequences
+    }
+}
+
+/// Gated recurrent unit over (batch, time, n_features) sequences. Gate weights are stacked column-wise
+/// in update, reset, candidate order, and `h_t = z * h_{t-1} + (1 - z) * candidate`.
+pub struct GRU<F: Float = f64> {
+    /// Shape (n_features, 3 * units).
+    pub weights_input: Array2<F>,
+    /// Shape (units, 3 * units).
+    pub weights_hidden: Array2<F>,
+    /// Length 3 * units.
+    pub biases: Array1<F>,
+    /// Output every hidden state as (batch, time, units) instead of only the last one as (batch, units).
+    pub return_sequences: bool,
+    /// Truncated backpropagation through time: gradients only flow back within chunks of this many steps,
+    /// counted back from the last step.
+    pub bptt_steps: Option<usize>,
+    pub weights_input_trainable: bool,
+    pub weights_hidden_trainable: bool,
+    pub biases_trainable: bool,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dweights_input: Option<Array2<F>>,
+    pub dweights_hidden: Option<Array2<F>>,
+    pub dbiases: Option<Array1<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    inputs: Option<Array2<F>>,
+    states: Vec<Array2<F>>,
+    // Activated update, reset and candidate values of every step.
+    gates: Vec<Array2<F>>,
+    weights_input_id: ParamId,
+    weights_hidden_id: ParamId,
+    biases_id: ParamId
+}
+
+impl<F: Float> GRU<F> {
+    pub fn new(n_features: usize, units: usize, return_sequences: bool) -> Self {
+        GRU {
+            weights_input: Initializer::XavierUniform.sample((n_features, 3 * units), n_features, units),
+            weights_hidden: Initializer::Orthogonal(1.0).initialize(units, 3 * units),
+            biases: Array1::zeros(3 * units),
+            return_sequences,
+            bptt_steps: None,
+            weights_input_trainable: true,
+            weights_hidden_trainable: true,
+            biases_trainable: true,
+            outputs: None,
+            dweights_input: None,
+            dweights_hidden: None,
+            dbiases: None,
+            dinputs: None,
+            inputs: None,
+            states: Vec::new(),
+            gates: Vec::new(),
+            weights_input_id: ParamId::unique(),
+            weights_hidden_id: ParamId::unique(),
+            biases_id: ParamId::unique()
+        }
+    }
+}
+
+impl<F: Float> Module<F> for GRU<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let (n_features, units) = (self.weights_input.nrows(), self.units());
+        let (inputs, steps) = sequence_rows(inputs, n_features)?;
+        let candidate_weights = self.weights_hidden.slice(s![.., 2 * units..]);
+
+        let mut states = vec![Array2::zeros((inputs.nrows(), units))];
+        let mut gates = Vec::with_capacity(steps);
+        for t in 0..steps {
+            let h = &states[t];
+            let mut z = step(&inputs, t, n_features).dot(&self.weights_input) + &self.biases;
+            let mut update_reset = z.slice_mut(s![.., ..2 * units]);
+            update_reset += &h.dot(&self.weights_hidden.slice(s![.., ..2 * units]));
+            update_reset.mapv_inplace(sigmoid);
+
+            let candidate = (&z.slice(s![.., 2 * units..]) + &(&step(&z, 1, units) * h).dot(&candidate_weights))
+                .mapv(|v| v.tanh());
+            z.slice_mut(s![.., 2 * units..]).assign(&candidate);
+
+            let update = step(&z, 0, units);
+            states.push(&update * h + &update.mapv(|u| F::one() - u) * &candidate);
+            gates.push(z);
+        }
+
+        self.outputs = Some(sequence_outputs(&states, self.return_sequences));
+        self.states = states;
+        self.gates = gates;
+        self.inputs = Some(inputs.into_owned());
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        let inputs = self.inputs.as_ref().ok_or(NnError::CallOrder { missing: "inputs", call: "forward" })?;
+        let (n_features, units) = (self.weights_input.nrows(), self.units());
+        let steps = self.gates.len();
+        let dsteps = step_gradients(dvalues, self.outputs()?, steps, units, self.return_sequences)?;
+        let candidate_weights = self.weights_hidden.slice(s![.., 2 * units..]);
+
+        let mut dweights_input = Array2::zeros(self.weights_input.raw_dim());
+        let mut dweights_hidden = Array2::<F>::zeros(self.weights_hidden.raw_dim());
+        let mut dbiases = Array1::zeros(3 * units);
+        let mut dinputs = Array2::zeros(inputs.raw_dim());
+        let mut dnext = Array2::zeros((inputs.nrows(), units));
+
+        for t in (0..steps).rev() {
+            let (gates, h) = (&self.gates[t], &self.states[t]);
+            let (update, reset, candidate) = (step(gates, 0, units), step(gates, 1, units), step(gates, 2, units));
+            let reset_h = &reset * h;
+
+            let dh = &dnext + &dsteps[t];
+            let mut dz = Array2::zeros(gates.raw_dim());
+            dz.slice_mut(s![.., ..units]).assign(&(&dh * &(h - &candidate) * update.mapv(|v| v * (F::one() - v))));
+            dz.slice_mut(s![.., 2 * units..]).assign(&(&dh * &update.mapv(|v| F::one() - v) * candidate.mapv(|v| F::one() - v * v)));
+            let dreset_h = step(&dz, 2, units).dot(&candidate_weights.t());
+            dz.slice_mut(s![.., units..2 * units]).assign(&(&dreset_h * h * reset.mapv(|v| v * (F::one() - v))));
+
+            dweights_input += &step(inputs, t, n_features).t().dot(&dz);
+            let mut dgates_hidden = dweights_hidden.slice_mut(s![.., ..2 * units]);
+            dgates_hidden += &h.t().dot(&dz.slice(s![.., ..2 * units]));
+            let mut dcandidate_hidden = dweights_hidden.slice_mut(s![.., 2 * units..]);
+            dcandidate_hidden += &reset_h.t().dot(&step(&dz, 2, units));
+            dbiases += &dz.sum_axis(Axis(0));
+            dinputs.slice_mut(s![.., t * n_features..(t + 1) * n_features]).assign(&dz.dot(&self.weights_input.t()));
+
+            if truncated_at(t, steps, self.bptt_steps)? {
+                dnext.fill(F::zero());
+            }
+
+            else {
+                dnext = &dh * &update 
+                    + &dreset_h * &reset 
+                    + dz.slice(s![.., ..2 * units]).dot(&self.weights_hidden.slice(s![.., ..2 * units]).t());
+            }
+        }
+
+        self.dweights_input = Some(dweights_input);
+        self.dweights_hidden = Some(dweights_hidden);
+        self.dbiases = Some(dbiases);
+        self.dinputs = Some(reshape(dinputs, &[inputs.nrows(), steps, n_features]));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dweights_input = self.dweights_input.as_ref().ok_or(NnError::CallOrder { missing: "dweights_input", call: "backward" })?;
+        let dweights_hidden = self.dweights_hidden.as_ref().ok_or(NnError::CallOrder { missing: "dweights_hidden", call: "backward" })?;
+        let dbiases = self.dbiases.as_ref().ok_or(NnError::CallOrder { missing: "dbiases", call: "backward" })?;
+
+        Ok(vec![
+            Param { 
+                id: self.weights_input_id, 
+                values: self.weights_input.view_mut().into_dyn(), 
+                grads: dweights_input.view().into_dyn(), 
+                trainable: self.weights_input_trainable 
+            },
+            Param { 
+                id: self.weights_hidden_id, 
+                values: self.weights_hidden.view_mut().into_dyn(), 
+                grads: dweights_hidden.view().into_dyn(), 
+                trainable: self.weights_hidden_trainable 
+            },
+            Param { 
+                id: self.biases_id, 
+                values: self.biases.view_mut().into_dyn(), 
+                grads: dbiases.view().into_dyn(), 
+                trainable: self.biases_trainable 
+            }
+        ])
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.weights_input_trainable = trainable;
+        self.weights_hidden_trainable = trainable;
+        self.biases_trainable = trainable;
+    }
+}
+
+impl<F: Float> Recurrent<F> for GRU<F> {
+    fn n_features(&self) -> usize {
+        self.weights_input.nrows()
+    }
+
+    fn units(&self) -> usize {
+        self.weights_hidden.nrows()
+    }
+
+    fn return_sequences(&self) -> bool {
+        self.return_sequences
+    }
+}
+
+/// Runs one recurrent layer over the sequence and another over its reverse, then merges their outputs.
+/// With `return_sequences`, the backward outputs are flipped back so both directions line up per step;
+/// `Merge::Concat` joins them along the last axis.
+pub struct Bidirectional<F: Float = f64> {
+    pub merge: Merge,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    forward_layer: Box<dyn Recurrent<F>>,
+    backward_layer: Box<dyn Recurrent<F>>
+}
+
+impl<F: Float> Bidirectional<F> {
+    /// Both layers must agree on `n_features`, `units` and `return_sequences`.
+    pub fn new<R: Recurrent<F> + 'static>(forward_layer: R, backward_layer: R, merge: Merge) -> Result<Self> {
+        let signature = |layer: &R| [layer.n_features(), layer.units(), layer.return_sequences() as usize];
+        if signature(&forward_layer) != signature(&backward_layer) {
+            return Err(NnError::ShapeMismatch { 
+                expected: signature(&forward_layer).to_vec(), 
+                found: signature(&backward_layer).to_vec() 
+            });
+        }
+
+        Ok(Bidirectional {
+            merge,
+            outputs: None,
+            dinputs: None,
+            forward_layer: Box::new(forward_layer),
+            backward_layer: Box::new(backward_layer)
+        })
+    }
+}
+
+impl<F: Float> Module<F> for Bidirectional<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        self.forward_layer.forward(inputs)?;
+        self.backward_layer.forward(&reverse_time(inputs))?;
+
+        let forward_outputs = self.forward_layer.outputs()?;
+        let backward_outputs = if self.forward_layer.return_sequences() {
+            reverse_time(self.backward_layer.outputs()?)
+        }
+
+        else {
+            self.backward_layer.outputs()?.clone()
+        };
+
+        self.outputs = Some(match self.merge {
+            Merge::Concat => {
+                let last = Axis(forward_outputs.ndim() - 1);
+                concatenate(last, &[forward_outputs.view(), backward_outputs.view()]).expect("Both directions share their shape")
+            },
+            Merge::Sum => forward_outputs + &backward_outputs
+        });
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+
+        let (dforward, dbackward) = match self.merge {
+            Merge::Concat => {
+                let (last, units) = (Axis(dvalues.ndim() - 1), self.forward_layer.units());
+                (
+                    dvalues.slice_axis(last, Slice::from(..units)).to_owned(), 
+                    dvalues.slice_axis(last, Slice::from(units..)).to_owned()
+                )
+            },
+            Merge::Sum => (dvalues.clone(), dvalues.clone())
+        };
+        let dbackward = if self.forward_layer.return_sequences() { reverse_time(&dbackward) } else { dbackward };
+
+        self.forward_layer.backward(&dforward)?;
+        self.backward_layer.backward(&dbackward)?;
+        self.dinputs = Some(self.forward_layer.dinputs()? + &reverse_time(self.backward_layer.dinputs()?));
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let mut params = self.forward_layer.params()?;
+        params.extend(self.backward_layer.params()?);
+        Ok(params)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.forward_layer.set_training(training);
+        self.backward_layer.set_training(training);
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.forward_layer.set_trainable(trainable);
+        self.backward_layer.set_trainable(trainable);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{s, ArrayD};
+
+    use super::*;
+    use crate::testing::{assert_gradients, random};
+
+    #[test]
+    fn rnn_gradients() {
+        for return_sequences in [false, true] {
+            assert_gradients(&mut RNN::new(3, 4, return_sequences), &random(&[2, 5, 3], 1));
+        }
+    }
+
+    #[test]
+    fn lstm_gradients() {
+        for return_sequences in [false, true] {
+            assert_gradients(&mut LSTM::new(3, 4, return_sequences), &random(&[2, 5, 3], 1));
+        }
+    }
+
+    #[test]
+    fn truncated_backpropagation_stops_at_chunk_boundaries() {
+        let mut rnn = RNN::new(2, 3, false);
+        rnn.bptt_steps = Some(2);
+        rnn.forward(&random(&[2, 4, 2], 1)).unwrap();
+        rnn.backward(&random(&[2, 3], 2)).unwrap();
+
+        // Only the last chunk, steps 2 and 3, receives the gradient of the final state.
+        let dinputs = rnn.dinputs().unwrap();
+        assert!(dinputs.slice(s![.., ..2, ..]).iter().all(|&v| v == 0.0));
+        assert!(dinputs.slice(s![.., 2.., ..]).iter().all(|&v| v != 0.0));
+
+        rnn.bptt_steps = Some(0);
+        assert!(matches!(rnn.backward(&random(&[2, 3], 2)), Err(NnError::InvalidHyperparameter(_))));
+    }
+
+    // Number of trailing steps whose inputs receive a gradient from the last state alone.
+    fn steps_reached(layer: &mut dyn Module, steps: usize) -> usize {
+        layer.forward(&random(&[2, steps, 2], 1)).unwrap();
+        layer.backward(&random(&[2, 3], 2)).unwrap();
+        let dinputs = layer.dinputs().unwrap();
+        (0..steps).filter(|&t| dinputs.slice(s![.., t, ..]).iter().any(|&v| v != 0.0)).count()
+    }
+
+    #[test]
+    fn last_state_reaches_exactly_bptt_steps() {
+        // Sequence lengths that are not multiples of the chunk size used to shorten the last chunk.
+        for (steps, k) in [(5, 2), (7, 3), (5, 5), (3, 4)] {
+            let mut rnn = RNN::new(2, 3, false);
+            rnn.bptt_steps = Some(k);
+            let mut lstm = LSTM::new(2, 3, false);
+            lstm.bptt_steps = Some(k);
+            let mut gru = GRU::new(2, 3, false);
+            gru.bptt_steps = Some(k);
+
+            let expected = k.min(steps);
+            assert_eq!(steps_reached(&mut rnn, steps), expected, "RNN steps {} k {}", steps, k);
+            assert_eq!(steps_reached(&mut lstm, steps), expected, "LSTM steps {} k {}", steps, k);
+            assert_eq!(steps_reached(&mut gru, steps), expected, "GRU steps {} k {}", steps, k);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_and_misshaped_sequences() {
+        let empty: ArrayD<f64> = ArrayD::zeros(vec![2, 0, 3]);
+        for return_sequences in [false, true] {
+            assert!(matches!(RNN::new(3, 4, return_sequences).forward(&empty), Err(NnError::EmptyBatch)));
+            assert!(matches!(LSTM::new(3, 4, return_sequences).forward(&empty), Err(NnError::EmptyBatch)));
+            assert!(matches!(GRU::new(3, 4, return_sequences).forward(&empty), Err(NnError::EmptyBatch)));
+        }
+
+        let mut rnn: RNN = RNN::new(3, 4, true);
+        assert!(matches!(rnn.forward(&ArrayD::zeros(vec![2, 5, 2])), Err(NnError::ShapeMismatch { .. })));
+        assert!(matches!(rnn.forward(&ArrayD::zeros(vec![2, 3])), Err(NnError::RankMismatch { expected: 3, found: 2 })));
+    }
+
+    #[test]
+    fn gru_gradients() {
+        for return_sequences in [false, true] {
+            assert_gradients(&mut GRU::new(3, 4, return_sequences), &random(&[2, 5, 3], 1));
+        }
+    }
+
+    #[test]
+    fn bidirectional_gradients() {
+        for merge in [Merge::Concat, Merge::Sum] {
+            for return_sequences in [false, true] {
+                let mut layer = Bidirectional::new(GRU::new(3, 4, return_sequences), GRU::new(3, 4, return_sequences), merge).unwrap();
+                assert_gradients(&mut layer, &random(&[2, 5, 3], 1));
+            }
+        }
+    }
+
+    #[test]
+    fn bidirectional_backward_layer_reads_reversed_time() {
+        let inputs = random(&[2, 5, 3], 1);
+        let backward_layer = RNN::new(3, 4, true);
+        let mut reference = RNN::new(3, 4, true);
+        reference.weights_input = backward_layer.weights_input.clone();
+        reference.weights_hidden = backward_layer.weights_hidden.clone();
+        reference.forward(&reverse_time(&inputs)).unwrap();
+
+        let mut layer = Bidirectional::new(RNN::new(3, 4, true), backward_layer, Merge::Concat).unwrap();
+        layer.forward(&inputs).unwrap();
+        let outputs = layer.outputs().unwrap();
+        assert_eq!(outputs.shape(), &[2, 5, 8]);
+
+        // Step t of the backward half has seen steps t.. of the input, like step T-1-t of the reversed run.
+        let expected = reverse_time(reference.outputs().unwrap());
+        assert_eq!(outputs.slice(s![.., .., 4..]).into_dyn(), expected);
+    }
+
+    #[test]
+    fn bidirectional_rejects_mismatched_layers() {
+        let layer: Result<Bidirectional> = Bidirectional::new(LSTM::new(3, 4, true), LSTM::new(3, 5, true), Merge::Sum);
+        assert!(matches!(layer, Err(NnError::ShapeMismatch { .. })));
+        assert!(Bidirectional::<f64>::new(LSTM::new(3, 4, true), LSTM::new(3, 4, false), Merge::Sum).is_err());
+    }
+}
\ No newline at end of file
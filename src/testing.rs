@@ -0,0 +1,91 @@
+//! Finite-difference gradient checks shared by the module tests.
+
+use ndarray::{ArrayD, IxDyn};
+use ndarray_rand::RandomExt;
+use rand::{rngs::StdRng, SeedableRng};
+use rand_distr::StandardNormal;
+
+use crate::{float::Float, module::Module};
+
+const STEP: f64 = 1e-5;
+const TOLERANCE: f64 = 1e-6;
+
+/// Standard normal values of the given shape, drawn from a fixed seed so failures reproduce.
+pub fn random(shape: &[usize], seed: u64) -> ArrayD<f64> {
+    ArrayD::random_using(IxDyn(shape), StandardNormal, &mut StdRng::seed_from_u64(seed))
+}
+
+// Scalar objective `sum(outputs * projection)`, whose gradient with respect to the outputs is `projection`.
+fn objective<F: Float>(module: &mut dyn Module<F>, inputs: &ArrayD<F>, projection: &ArrayD<F>) -> f64 {
+    module.forward(inputs).unwrap();
+    module.outputs().unwrap().iter().zip(projection).map(|(&output, &weight)| output.as_f64() * weight.as_f64()).sum()
+}
+
+fn relative_error(analytic: f64, numeric: f64) -> f64 {
+    (analytic - numeric).abs() / (analytic.abs() + numeric.abs()).max(1e-3)
+}
+
+fn param_value<F: Float>(module: &mut dyn Module<F>, param: usize, index: usize) -> &mut F {
+    let values = module.params().unwrap().swap_remove(param).values;
+    values.into_iter().nth(index).unwrap()
+}
+
+// Largest relative errors of `dinputs` (when `check_inputs`) and of every parameter gradient.
+fn gradient_errors<F: Float>(module: &mut dyn Module<F>, inputs: &ArrayD<F>, check_inputs: bool, step: f64) -> (f64, f64) {
+    module.forward(inputs).unwrap();
+    let projection = random(module.outputs().unwrap().shape(), 7).mapv(F::cast);
+    module.backward(&projection).unwrap();
+
+    let dinputs = module.dinputs().unwrap().clone();
+    let grads: Vec<ArrayD<F>> = module.params().unwrap().iter().map(|param| param.grads.to_owned()).collect();
+    let step_f = F::cast(step);
+
+    let mut input_error: f64 = 0.0;
+    if check_inputs {
+        let mut shifted = inputs.clone();
+        for (index, analytic) in dinputs.iter().enumerate() {
+            let original = shifted.as_slice().unwrap()[index];
+            shifted.as_slice_mut().unwrap()[index] = original + step_f;
+            let plus = objective(module, &shifted, &projection);
+            shifted.as_slice_mut().unwrap()[index] = original - step_f;
+            let minus = objective(module, &shifted, &projection);
+            shifted.as_slice_mut().unwrap()[index] = original;
+
+            input_error = input_error.max(relative_error(analytic.as_f64(), (plus - minus) / (2.0 * step)));
+        }
+    }
+
+    let mut param_error: f64 = 0.0;
+    for (param, grad) in grads.iter().enumerate() {
+        for (index, analytic) in grad.iter().enumerate() {
+            let original = *param_value(module, param, index);
+            *param_value(module, param, index) = original + step_f;
+            let plus = objective(module, inputs, &projection);
+            *param_value(module, param, index) = original - step_f;
+            let minus = objective(module, inputs, &projection);
+            *param_value(module, param, index) = original;
+
+            param_error = param_error.max(relative_error(analytic.as_f64(), (plus - minus) / (2.0 * step)));
+        }
+    }
+
+    (input_error, param_error)
+}
+
+/// Asserts that `dinputs` and every parameter gradient match central finite differences.
+pub fn assert_gradients(module: &mut dyn Module, inputs: &ArrayD<f64>) {
+    assert_gradients_with(module, inputs, STEP, TOLERANCE);
+}
+
+/// Same as `assert_gradients` with an explicit step and tolerance, e.g. for the coarser precision of `f32`.
+pub fn assert_gradients_with<F: Float>(module: &mut dyn Module<F>, inputs: &ArrayD<F>, step: f64, tolerance: f64) {
+    let (input_error, param_error) = gradient_errors(module, inputs, true, step);
+    assert!(input_error < tolerance, "dinputs relative error {}", input_error);
+    assert!(param_error < tolerance, "parameter relative error {}", param_error);
+}
+
+/// Same as `assert_gradients` for modules whose inputs are not differentiable, such as token IDs.
+pub fn assert_param_gradients(module: &mut dyn Module, inputs: &ArrayD<f64>) {
+    let (_, param_error) = gradient_errors(module, inputs, false, STEP);
+    assert!(param_error < TOLERANCE, "parameter relative error {}", param_error);
+}
\ No newline at end of file
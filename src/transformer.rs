@@ -0,0 +1,343 @@
+use ndarray::{s, Array2, ArrayD, Axis, Ix2, Ix3};
+
+use crate::{
+    activations::ReLU, 
+    attention::MultiHeadAttention, 
+    dropout::Dropout, 
+    error::{NnError, Result}, 
+    float::Float, 
+    initializers::Initializer, 
+    layer::Layer, 
+    module::{Module, Param, ParamId}, 
+    normalization::LayerNorm, 
+    utils::{check_shape, to_rank}
+};
+
+// Number of time steps of (batch, time, embed_dim) `inputs`.
+fn time_steps<F: Float>(inputs: &ArrayD<F>, embed_dim: usize) -> Result<usize> {
+    let (n_samples, steps, _) = to_rank::<F, Ix3>(inputs)?.dim();
+    check_shape(inputs, &[n_samples, steps, embed_dim])?;
+    Ok(steps)
+}
+
+/// Adds fixed sine/cosine position signals to (batch, time, embed_dim) sequences:
+/// `sin(t / 10000^(2i / embed_dim))` on even features, cosine on odd ones.
+pub struct SinusoidalPositionalEncoding<F: Float = f64> {
+    pub embed_dim: usize,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>
+}
+
+impl<F: Float> SinusoidalPositionalEncoding<F> {
+    pub fn new(embed_dim: usize) -> Self {
+        SinusoidalPositionalEncoding {
+            embed_dim,
+            outputs: None,
+            dinputs: None
+        }
+    }
+
+    /// The encodings of the first `steps` positions, of shape (steps, embed_dim).
+    pub fn encoding(&self, steps: usize) -> Array2<F> {
+        Array2::from_shape_fn((steps, self.embed_dim), |(t, i)| {
+            let angle = t as f64 / 10000f64.powf((i - i % 2) as f64 / self.embed_dim as f64);
+            F::cast(if i % 2 == 0 { angle.sin() } else { angle.cos() })
+        })
+    }
+}
+
+impl<F: Float> Module<F> for SinusoidalPositionalEncoding<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let steps = time_steps(inputs, self.embed_dim)?;
+        self.outputs = Some(inputs + &self.encoding(steps).into_dyn());
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+        self.dinputs = Some(dvalues.clone());
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+}
+
+/// Adds a trained vector per position to (batch, time, embed_dim) sequences.
+/// Sequences may be at most `max_len` steps long.
+pub struct LearnedPositionalEncoding<F: Float = f64> {
+    /// One row per position, of shape (max_len, embed_dim).
+    pub weights: Array2<F>,
+    pub weights_trainable: bool,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dweights: Option<Array2<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    weights_id: ParamId
+}
+
+impl<F: Float> LearnedPositionalEncoding<F> {
+    pub fn new(max_len: usize, embed_dim: usize) -> Self {
+        LearnedPositionalEncoding {
+            weights: Initializer::RandomNormal(0.02).initialize(max_len, embed_dim),
+            weights_trainable: true,
+            outputs: None,
+            dweights: None,
+            dinputs: None,
+            weights_id: ParamId::unique()
+        }
+    }
+}
+
+impl<F: Float> Module<F> for LearnedPositionalEncoding<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        let (max_len, embed_dim) = self.weights.dim();
+        let steps = time_steps(inputs, embed_dim)?;
+        if steps > max_len {
+            return Err(NnError::ShapeMismatch { 
+                expected: vec![inputs.len_of(Axis(0)), max_len, embed_dim], 
+                found: inputs.shape().to_vec() 
+            });
+        }
+
+        self.outputs = Some(inputs + &self.weights.slice(s![..steps, ..]).into_dyn());
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+
+        let steps = dvalues.len_of(Axis(1));
+        let mut dweights = Array2::zeros(self.weights.raw_dim());
+        dweights.slice_mut(s![..steps, ..]).assign(&to_rank::<F, Ix2>(&dvalues.sum_axis(Axis(0)))?);
+
+        self.dweights = Some(dweights);
+        self.dinputs = Some(dvalues.clone());
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let dweights = self.dweights.as_ref().ok_or(NnError::CallOrder { missing: "dweights", call: "backward" })?;
+        Ok(vec![Param { 
+            id: self.weights_id, 
+            values: self.weights.view_mut().into_dyn(), 
+            grads: dweights.view().into_dyn(), 
+            trainable: self.weights_trainable 
+        }])
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.weights_trainable = trainable;
+    }
+}
+
+/// Post-norm transformer encoder block over (batch, time, embed_dim) sequences:
+/// `h = LayerNorm(x + Dropout(Attention(x)))`, then `LayerNorm(h + Dropout(Dense(ReLU(Dense(h)))))`.
+/// Padding masks given to `set_padding_mask` are passed on to `attention`.
+pub struct TransformerEncoder<F: Float = f64> {
+    pub attention: MultiHeadAttention<F>,
+    pub attention_norm: LayerNorm<F>,
+    pub feed_forward_hidden: Layer<F>,
+    pub feed_forward_output: Layer<F>,
+    pub feed_forward_norm: LayerNorm<F>,
+
+    pub outputs: Option<ArrayD<F>>,
+    pub dinputs: Option<ArrayD<F>>,
+
+    activation: ReLU<F>,
+    attention_dropout: Dropout<F>,
+    feed_forward_dropout: Dropout<F>
+}
+
+impl<F: Float> TransformerEncoder<F> {
+    /// `feed_forward_dim` is the width of the hidden feed-forward layer; `dropout_rate` applies to both residual branches.
+    pub fn new(embed_dim: usize, num_heads: usize, feed_forward_dim: usize, dropout_rate: f64) -> Result<Self> {
+        Ok(TransformerEncoder {
+            attention: MultiHeadAttention::new(embed_dim, num_heads, false)?,
+            attention_norm: LayerNorm::new(embed_dim),
+            feed_forward_hidden: Layer::with_initializers(
+                embed_dim, feed_forward_dim, Initializer::HeUniform, Initializer::Zeros
+            ),
+            feed_forward_output: Layer::with_initializers(
+                feed_forward_dim, embed_dim, Initializer::XavierUniform, Initializer::Zeros
+            ),
+            feed_forward_norm: LayerNorm::new(embed_dim),
+            outputs: None,
+            dinputs: None,
+            activation: ReLU::new(),
+            attention_dropout: Dropout::new(dropout_rate)?,
+            feed_forward_dropout: Dropout::new(dropout_rate)?
+        })
+    }
+
+    pub fn embed_dim(&self) -> usize {
+        self.attention.embed_dim()
+    }
+}
+
+impl<F: Float> Module<F> for TransformerEncoder<F> {
+    fn forward(&mut self, inputs: &ArrayD<F>) -> Result<()> {
+        time_steps(inputs, self.embed_dim())?;
+
+        self.attention.forward(inputs)?;
+        self.attention_dropout.forward(self.attention.outputs()?)?;
+        self.attention_norm.forward(&(inputs + self.attention_dropout.outputs()?))?;
+
+        let hidden = self.attention_norm.outputs()?;
+        self.feed_forward_hidden.forward(hidden)?;
+        self.activation.forward(self.feed_forward_hidden.outputs()?)?;
+        self.feed_forward_output.forward(self.activation.outputs()?)?;
+        self.feed_forward_dropout.forward(self.feed_forward_output.outputs()?)?;
+        self.feed_forward_norm.forward(&(hidden + self.feed_forward_dropout.outputs()?))?;
+
+        self.outputs = Some(self.feed_forward_norm.outputs()?.clone());
+        Ok(())
+    }
+
+    fn backward(&mut self, dvalues: &ArrayD<F>) -> Result<()> {
+        check_shape(dvalues, self.outputs()?.shape())?;
+
+        self.feed_forward_norm.backward(dvalues)?;
+        let dresidual = self.feed_forward_norm.dinputs()?;
+        self.feed_forward_dropout.backward(dresidual)?;
+        self.feed_forward_output.backward(self.feed_forward_dropout.dinputs()?)?;
+        self.activation.backward(self.feed_forward_output.dinputs()?)?;
+        self.feed_forward_hidden.backward(self.activation.dinputs()?)?;
+
+        self.attention_norm.backward(&(dresidual + self.feed_forward_hidden.dinputs()?))?;
+        let dresidual = self.attention_norm.dinputs()?;
+        self.attention_dropout.backward(dresidual)?;
+        self.attention.backward(self.attention_dropout.dinputs()?)?;
+
+        self.dinputs = Some(dresidual + self.attention.dinputs()?);
+        Ok(())
+    }
+
+    fn outputs(&self) -> Result<&ArrayD<F>> {
+        self.outputs.as_ref().ok_or(NnError::CallOrder { missing: "outputs", call: "forward" })
+    }
+
+    fn dinputs(&self) -> Result<&ArrayD<F>> {
+        self.dinputs.as_ref().ok_or(NnError::CallOrder { missing: "dinputs", call: "backward" })
+    }
+
+    fn params(&mut self) -> Result<Vec<Param<'_, F>>> {
+        let mut params = self.attention.params()?;
+        params.extend(self.attention_norm.params()?);
+        params.extend(self.feed_forward_hidden.params()?);
+        params.extend(self.feed_forward_output.params()?);
+        params.extend(self.feed_forward_norm.params()?);
+        Ok(params)
+    }
+
+    fn set_training(&mut self, training: bool) {
+        self.attention_dropout.set_training(training);
+        self.feed_forward_dropout.set_training(training);
+    }
+
+    fn set_trainable(&mut self, trainable: bool) {
+        self.attention.set_trainable(trainable);
+        self.attention_norm.set_trainable(trainable);
+        self.feed_forward_hidden.set_trainable(trainable);
+        self.feed_forward_output.set_trainable(trainable);
+        self.feed_forward_norm.set_trainable(trainable);
+    }
+
+    fn set_padding_mask(&mut self, mask: Option<&Array2<bool>>) {
+        self.attention.set_padding_mask(mask);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{array, s, Array1};
+
+    use super::*;
+    use crate::{
+        embedding::Embedding, 
+        loss_functions::SoftmaxCategoricalCrossEntropy, 
+        model::{Model, Sequential}, 
+        optimizers::Adam, 
+        pooling::Flatten, 
+        testing::{assert_gradients, random}
+    };
+
+    #[test]
+    fn positional_encoding_gradients() {
+        assert_gradients(&mut SinusoidalPositionalEncoding::new(4), &random(&[2, 3, 4], 1));
+        assert_gradients(&mut LearnedPositionalEncoding::new(5, 4), &random(&[2, 3, 4], 1));
+    }
+
+    #[test]
+    fn sinusoidal_encoding_values() {
+        let encoding: Array2<f64> = SinusoidalPositionalEncoding::new(4).encoding(2);
+        let expected = array![[0.0, 1.0, 0.0, 1.0], [1f64.sin(), 1f64.cos(), 0.01f64.sin(), 0.01f64.cos()]];
+        assert!((&encoding - &expected).iter().all(|diff| diff.abs() < 1e-12));
+    }
+
+    #[test]
+    fn learned_encoding_rejects_long_sequences() {
+        let mut encoding: LearnedPositionalEncoding = LearnedPositionalEncoding::new(3, 4);
+        assert!(matches!(encoding.forward(&random(&[2, 4, 4], 1)), Err(NnError::ShapeMismatch { .. })));
+    }
+
+    #[test]
+    fn encoder_gradients() {
+        let mut encoder = TransformerEncoder::new(4, 2, 8, 0.0).unwrap();
+        assert_gradients(&mut encoder, &random(&[2, 3, 4], 1));
+        encoder.set_padding_mask(Some(&array![[false, false, false], [false, true, true]]));
+        assert_gradients(&mut encoder, &random(&[2, 3, 4], 1));
+    }
+
+    #[test]
+    fn encoder_masks_padding() {
+        let mut encoder = TransformerEncoder::new(4, 2, 8, 0.0).unwrap();
+        let inputs = random(&[1, 3, 4], 1);
+        encoder.forward(&inputs).unwrap();
+        let unpadded = encoder.outputs().unwrap().clone();
+
+        let mut padded = random(&[1, 4, 4], 2);
+        padded.slice_mut(s![.., ..3, ..]).assign(&inputs);
+        encoder.set_padding_mask(Some(&array![[false, false, false, true]]));
+        encoder.forward(&padded).unwrap();
+
+        let outputs = encoder.outputs().unwrap().slice(s![.., ..3, ..]).into_dyn();
+        assert!((&outputs - &unpadded).iter().all(|diff| diff.abs() < 1e-12));
+    }
+
+    #[test]
+    fn encoder_trains_on_shuffled_padded_batches() {
+        let x = Array2::from_shape_fn((9, 4), |(i, t)| if t <= i % 4 { ((i + t) % 4 + 1) as f64 } else { 0.0 });
+        let y = Array1::from_shape_fn(9, |i| i % 3);
+
+        let mut model = Sequential::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(Adam::new()));
+        model.padding_token = Some(0);
+        model.add(Embedding::new(5, 4));
+        model.add(LearnedPositionalEncoding::new(4, 4));
+        model.add(TransformerEncoder::new(4, 2, 8, 0.1).unwrap());
+        model.add(Flatten::new());
+        model.add(Layer::new(16, 3));
+
+        let history = model.fit(&x, &y, 2, 4, None).unwrap();
+        assert!(history.loss.iter().all(|loss| loss.is_finite()));
+
+        let predictions = model.predict(&x).unwrap();
+        let single = model.predict(&x.slice(s![8..9, ..]).to_owned()).unwrap();
+        assert!((&single.row(0) - &predictions.row(8)).iter().all(|diff| diff.abs() < 1e-12));
+    }
+}
\ No newline at end of file
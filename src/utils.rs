@@ -1,6 +1,6 @@
-#![allow(dead_code)]
+use ndarray::{Array, Array1, Array2, ArrayD, ArrayView, Axis, CowArray, Dimension, Ix2};
 
-use ndarray::{Array1, Array2, Axis};
+use crate::{error::{NnError, Result}, float::Float};
 
 pub fn linspace(start: f64, stop: f64, num: usize) -> Vec<f64> {
     if num < 2 {
@@ -10,36 +10,30 @@ pub fn linspace(start: f64, stop: f64, num: usize) -> Vec<f64> {
     (0..num).map(|i| start + step * i as f64).collect()
 }
 
-pub fn clip(a: &Array2<f64>, interval_min: f64, interval_max: f64) -> Array2<f64> {
+pub fn clip<F: Float>(a: &Array2<F>, interval_min: F, interval_max: F) -> Array2<F> {
     a.mapv(|x| x.clamp(interval_min, interval_max))
 }
 
-pub fn accuracy(y_pred: &Array2<f64>, y_true: &Array1<usize>) -> f64 {
-    let n = y_true.len() as f64;
-
-    let pred_classes: Array1<usize> = y_pred
-        .axis_iter(Axis(0))
-        .map(
-            |a| {
-                let array_max = a.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-            
-                a
-                    .iter()
-                    .position(|&x| x == array_max)
-                    .expect("No target found in array")
-            }
-        )
-        .collect();
-
-    let n_correct = pred_classes
-        .iter()
-        .zip(y_true.iter())
-        .filter(
-            |(p, t)| p == t
-        )
-        .count();
-
-    n_correct as f64 / n
+/// Fraction of rows of `y_pred` whose largest value sits at the true class.
+/// Rows that are entirely NaN, e.g. after training diverged, have no prediction and give an error.
+pub fn accuracy<F: Float>(y_pred: &Array2<F>, y_true: &Array1<usize>) -> Result<f64> {
+    if y_true.is_empty() {
+        return Err(NnError::EmptyBatch);
+    }
+    if y_pred.nrows() != y_true.len() {
+        return Err(NnError::ShapeMismatch { expected: vec![y_true.len()], found: vec![y_pred.nrows()] });
+    }
+
+    let mut n_correct = 0;
+    for (sample, (row, &class)) in y_pred.axis_iter(Axis(0)).zip(y_true.iter()).enumerate() {
+        let array_max = row.iter().cloned().fold(F::neg_infinity(), F::max);
+        match row.iter().position(|&x| x == array_max) {
+            Some(predicted) => n_correct += (predicted == class) as usize,
+            None => return Err(NnError::NonFinite(format!("every prediction for sample {} is NaN", sample)))
+        }
+    }
+
+    Ok(n_correct as f64 / y_true.len() as f64)
 }
 
 pub fn to_sparse(one_hot: &Array2<usize>) -> Array1<usize> {
@@ -64,6 +58,58 @@ pub fn to_one_hot(sparse: Array1<usize>, n_classes: usize) -> Array2<usize> {
     })
 }
 
-pub fn diagflat(a: &Array2<f64>) -> Array2<f64> {
+pub fn diagflat<F: Float>(a: &Array2<F>) -> Array2<F> {
     Array2::eye(a.dim().0) * a
+}
+
+// Views `values` with the static rank `D`.
+pub(crate) fn to_rank<F: Float, D: Dimension>(values: &ArrayD<F>) -> Result<ArrayView<'_, F, D>> {
+    values
+        .view()
+        .into_dimensionality::<D>()
+        .map_err(|_| NnError::RankMismatch { expected: D::NDIM.unwrap_or(0), found: values.ndim() })
+}
+
+// Merges every axis but the last into rows. Standard-layout inputs are viewed, not copied.
+pub(crate) fn to_rows<F: Float>(values: &ArrayD<F>) -> Result<CowArray<'_, F, Ix2>> {
+    if values.ndim() < 2 {
+        return Err(NnError::RankMismatch { expected: 2, found: values.ndim() });
+    }
+
+    let (leading, last) = values.shape().split_at(values.ndim() - 1);
+    Ok(values.to_shape((leading.iter().product(), last[0])).expect("Element count is unchanged"))
+}
+
+// Reshapes an owned array, copying only when it is not in standard layout.
+pub(crate) fn reshape<F: Float, D: Dimension>(values: Array<F, D>, shape: &[usize]) -> ArrayD<F> {
+    let values = if values.is_standard_layout() { values } else { values.as_standard_layout().into_owned() };
+    values.into_shape(shape).expect("Element count is unchanged")
+}
+
+// Errors unless `values` has exactly `shape`.
+pub(crate) fn check_shape<F: Float>(values: &ArrayD<F>, shape: &[usize]) -> Result<()> {
+    if values.shape() != shape {
+        return Err(NnError::ShapeMismatch { expected: shape.to_vec(), found: values.shape().to_vec() });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn accuracy_counts_argmax_hits() {
+        let y_pred = array![[0.1, 0.9], [0.8, 0.2], [f64::NAN, 0.3]];
+        assert_eq!(accuracy(&y_pred, &array![1, 1, 1]).unwrap(), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn accuracy_rejects_all_nan_rows() {
+        let y_pred = array![[0.1, 0.9], [f64::NAN, f64::NAN]];
+        assert!(matches!(accuracy(&y_pred, &array![1, 0]), Err(NnError::NonFinite(_))));
+        assert!(matches!(accuracy(&y_pred, &array![1]), Err(NnError::ShapeMismatch { .. })));
+    }
 }
\ No newline at end of file
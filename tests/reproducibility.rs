@@ -0,0 +1,48 @@
+//! Seeded runs must repeat bit for bit. This is the only test of its binary, so no other test can
+//! draw from the crate-wide generator between `set_seed` and the draws being compared.
+
+use ndarray::{Array1, Array2, ArrayD};
+use nns::prelude::*;
+
+struct Run {
+    weights: Array2<f64>,
+    x: Array2<f64>,
+    y: Array1<usize>,
+    mask: ArrayD<f64>,
+    trained: Array2<f64>
+}
+
+fn run(seed: u64) -> Run {
+    set_seed(seed);
+    let weights = Layer::<f64>::new(4, 3).weights;
+    let (x, y) = spiral_data(20, 3);
+
+    let mut dropout = Dropout::new(0.5).unwrap();
+    dropout.forward(&ArrayD::ones(vec![4, 6])).unwrap();
+    let mask = dropout.outputs().unwrap().clone();
+
+    // Training draws for the initial weights and every epoch's shuffle.
+    let mut model = Sequential::new(Box::new(SoftmaxCategoricalCrossEntropy::new()), Box::new(Adam::new()));
+    model.add(Layer::new(2, 8));
+    model.add(ReLU::new());
+    model.add(Dropout::new(0.1).unwrap());
+    model.add(Layer::new(8, 3));
+    model.fit(&x, &y, 3, 16, None).unwrap();
+    let trained = model.predict(&x).unwrap();
+
+    Run { weights, x, y, mask, trained }
+}
+
+#[test]
+fn seeded_runs_repeat_exactly() {
+    let (first, second) = (run(42), run(42));
+    assert_eq!(first.weights, second.weights);
+    assert_eq!(first.x, second.x);
+    assert_eq!(first.y, second.y);
+    assert_eq!(first.mask, second.mask);
+    assert_eq!(first.trained, second.trained);
+
+    let other = run(43);
+    assert_ne!(first.weights, other.weights);
+    assert_ne!(first.mask, other.mask);
+}
\ No newline at end of file